@@ -1,17 +1,82 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use tui::widgets::ListState;
 
 use crate::client::api::ContentUrlProvider;
-use crate::format::format_html;
+use crate::format::{format_html, Link};
+use crate::history::{History, Location};
 use crate::keybinds::{display_key, Keybinds};
-use crate::model::{Board, Thread, ThreadPost};
+use crate::loader::Request;
+use crate::lru::Lru;
+use crate::model::{Board, CatalogSort, Thread, ThreadList, ThreadListMode, ThreadPost};
+use crate::replies::ReplyGraph;
 use crate::style::SelectedField;
 
 pub(crate) struct App {
     pub(crate) boards: ItemLIst<Board>,
+    /// NSFW boards taken out of the board list, with their original position
+    hidden_boards: Vec<(usize, Board)>,
+    show_nsfw: bool,
     pub(crate) threads: ItemLIst<Thread>,
     pub(crate) thread: ItemLIst<ThreadPost>,
+    /// Position of each post of the opened thread, by post number
+    post_index: HashMap<usize, usize>,
+    /// Positions of the posts quotes were followed from, the latest last
+    quote_stack: Vec<usize>,
+    reply_graph: ReplyGraph,
+    /// Post whose replies are being cycled through, and the index of the selected reply
+    reply_cycle: Option<(usize, usize)>,
+    /// Position of each catalog thread in bump order, by thread number
+    bump_order: HashMap<usize, usize>,
+    /// Posts with revealed spoilers, by board and post number
+    revealed_spoilers: HashMap<String, HashSet<usize>>,
+    /// Posts shown without cutting their comment, by board and post number
+    expanded_posts: HashMap<String, HashSet<usize>>,
+    /// Post shown in the reader overlay
+    reader: Option<Reader>,
+    /// Quote previews over the thread, the innermost last
+    previews: Vec<Preview>,
+    /// Post whose conversation is shown instead of the whole thread
+    conversation: Option<usize>,
+    /// Show the thread as a tree of replies instead of in chronological order
+    tree_view: bool,
+    /// Chronological position and depth of each post of the thread tree, by position
+    tree: Vec<(usize, usize)>,
+    /// Posts of the thread tree with their replies collapsed, by post number
+    collapsed_posts: HashSet<usize>,
+    /// Links of the selected post to other threads and boards, to choose one to open
+    link_menu: Option<LinkMenu>,
+    /// Thread and post to select once the thread is loaded, from a link
+    pending_post: Option<(usize, usize)>,
+    /// Thread to select once the thread list is loaded
+    pending_thread: Option<usize>,
+    /// Requests the shown thread list and thread were loaded with, `None` while they are not loaded
+    threads_source: Option<Request>,
+    thread_source: Option<Request>,
+    /// Thread lists fetched before on each board, by the request they were fetched with
+    cached_thread_lists: HashMap<String, Lru<Request, CachedThreads>>,
+    /// Request of the thread list last shown of each board
+    board_requests: HashMap<String, Request>,
+    /// Threads fetched before, by the request they were fetched with
+    cached_threads: Lru<Request, CachedThread>,
+    history: History<Visit>,
+    /// Name of the archive the shown thread was fetched from, if it is gone from the imageboard
+    thread_archive: Option<String>,
     shown_state: ShownState,
     help_bar: HelpBar,
+    spinner: usize,
+    /// Unix time of the last tick, for relative timestamps
+    now: u64,
+    status: Option<String>,
+}
+
+/// Add the post to the posts of the board, or remove it if it is there
+fn toggle_post(posts: &mut HashMap<String, HashSet<usize>>, board: String, no: usize) {
+    let board_posts = posts.entry(board).or_default();
+    if !board_posts.remove(&no) {
+        board_posts.insert(no);
+    }
 }
 
 /// Format 2D array as table, with aligned columns
@@ -65,6 +130,26 @@ impl App {
             open_media,
             fullscreen,
             reload,
+            cancel,
+            retry,
+            catalog,
+            catalog_sort,
+            archive,
+            board_info,
+            toggle_nsfw,
+            reveal_spoilers,
+            copy_code,
+            expand_post,
+            read_post,
+            follow_quote,
+            quote_back,
+            preview_quotes,
+            conversation,
+            tree_view,
+            collapse_replies,
+            cross_links,
+            history_back,
+            history_forward,
             help,
             quit,
         ];
@@ -79,7 +164,7 @@ impl App {
             ],
             &[
                 "move quickly:",
-                &format!("{quick_up}, {quick_down}, {quick_left}, {quick_right}"),
+                &format!("{quick_up}, {quick_down}"),
                 "copy thread/post url:",
                 &copy_thread,
             ],
@@ -96,7 +181,67 @@ impl App {
                 &open_thread,
             ],
             &["previous page:", &page_previous, "reload page:", &reload],
-            &["quit:", &quit, "open media url in browser:", &open_media],
+            &[
+                "cancel loading:",
+                &cancel,
+                "open media url in browser:",
+                &open_media,
+            ],
+            &["retry failed request:", &retry, "toggle catalog:", &catalog],
+            &[
+                "toggle archive:",
+                &archive,
+                "change catalog sort:",
+                &catalog_sort,
+            ],
+            &[
+                "board info:",
+                &board_info,
+                "show/hide NSFW boards:",
+                &toggle_nsfw,
+            ],
+            &[
+                "reveal spoilers:",
+                &reveal_spoilers,
+                "copy code:",
+                &copy_code,
+            ],
+            &[
+                "expand/collapse post:",
+                &expand_post,
+                "read post in full:",
+                &read_post,
+            ],
+            &[
+                "follow quote:",
+                &follow_quote,
+                "back from quote:",
+                &quote_back,
+            ],
+            &[
+                "previous/next reply:",
+                &format!("{quick_left}, {quick_right}"),
+                "preview quoted posts:",
+                &preview_quotes,
+            ],
+            &[
+                "show post conversation:",
+                &conversation,
+                "toggle tree view:",
+                &tree_view,
+            ],
+            &[
+                "collapse/expand replies:",
+                &collapse_replies,
+                "links to other threads:",
+                &cross_links,
+            ],
+            &[
+                "back/forward:",
+                &format!("{history_back}, {history_forward}"),
+                "quit:",
+                &quit,
+            ],
         ];
 
         let text = format!(
@@ -110,30 +255,918 @@ impl App {
 
         Self {
             boards: ItemLIst::new(boards),
+            hidden_boards: vec![],
+            show_nsfw: true,
             threads: ItemLIst::new(threads),
+            post_index: post_index(&thread),
+            reply_graph: ReplyGraph::new(&thread, None),
             thread: ItemLIst::new(thread),
+            quote_stack: vec![],
+            reply_cycle: None,
+            bump_order: HashMap::new(),
+            revealed_spoilers: HashMap::new(),
+            expanded_posts: HashMap::new(),
+            reader: None,
+            previews: vec![],
+            conversation: None,
+            tree_view: false,
+            tree: vec![],
+            collapsed_posts: HashSet::new(),
+            link_menu: None,
+            pending_post: None,
+            pending_thread: None,
+            threads_source: None,
+            thread_source: None,
+            cached_thread_lists: HashMap::new(),
+            board_requests: HashMap::new(),
+            cached_threads: Lru::new(CACHED_THREADS),
+            history: History::default(),
+            thread_archive: None,
             shown_state: ShownState {
                 board_list: false,
                 thread_list: false,
                 thread: false,
+                board_info: false,
             },
             help_bar: HelpBar {
                 shown: false,
                 title: format!("Help (\"{help}\" to toggle)"),
                 text,
             },
+            spinner: 0,
+            now: unix_now(),
+            status: None,
         }
     }
 
+    pub(crate) fn fill_boards(&mut self, boards: Vec<Board>) {
+        self.boards = ItemLIst::new(boards);
+        self.hidden_boards.clear();
+        if !self.show_nsfw {
+            self.hide_nsfw_boards();
+        }
+    }
+
+    pub(crate) fn show_nsfw(&self) -> bool {
+        self.show_nsfw
+    }
+
+    /// Show or hide NSFW boards, keeping the selected board selected if it is still shown
+    pub(crate) fn toggle_nsfw(&mut self) {
+        let selected = self.selected_board().map(|board| board.board().to_string());
+
+        self.show_nsfw ^= true;
+        if self.show_nsfw {
+            for (i, board) in self.hidden_boards.drain(..) {
+                self.boards.items.insert(i, board);
+            }
+        } else {
+            self.hide_nsfw_boards();
+        }
+
+        let position = self
+            .boards
+            .items
+            .iter()
+            .position(|board| Some(board.board()) == selected.as_deref());
+        let fallback = (!self.boards.items.is_empty()).then_some(0);
+        self.boards.state.select(position.or(fallback));
+    }
+
+    fn hide_nsfw_boards(&mut self) {
+        let (hidden, shown): (Vec<_>, Vec<_>) = self
+            .boards
+            .items
+            .drain(..)
+            .enumerate()
+            .partition(|(_, board)| board.nsfw());
+
+        self.boards.items = shown.into_iter().map(|(_, board)| board).collect();
+        self.hidden_boards = hidden;
+    }
+
     pub(crate) fn fill_threads(&mut self, threads: Vec<Thread>) {
         self.threads = ItemLIst::new(threads);
+        self.threads_source = None;
+
+        if self.threads.items.is_empty() {
+            return;
+        }
+        if let Some(no) = self.pending_thread.take() {
+            let position = self
+                .threads
+                .items
+                .iter()
+                .position(|thread| thread.posts().first().map(ThreadPost::no) == Some(no));
+            self.threads.state.select(position);
+        }
     }
 
-    pub(crate) fn fill_thread(&mut self, thread: Vec<ThreadPost>) {
+    /// Keep the thread list loaded with the request, for showing it again without fetching it
+    pub(crate) fn cache_threads(&mut self, request: Request, thread_list: &ThreadList) {
+        self.threads_source = Some(request.clone());
+        let Some(board) = request.board().map(str::to_string) else {
+            return;
+        };
+
+        let mut threads = self.threads.items.clone();
+        // catalog threads are kept in bump order, to be sorted again when restored
+        if matches!(request, Request::Catalog { .. }) {
+            threads.sort_by_key(|thread| {
+                thread
+                    .posts()
+                    .first()
+                    .and_then(|op| self.bump_order.get(&op.no()).copied())
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        let cached = CachedThreads {
+            threads,
+            thread_list: thread_list.clone(),
+            selected: self.selected_thread_no().map(|no| no as usize),
+        };
+
+        self.board_requests.insert(board.clone(), request.clone());
+        self.cached_thread_lists
+            .entry(board)
+            .or_insert_with(|| Lru::new(CACHED_THREAD_LISTS))
+            .insert(request, cached);
+    }
+
+    /// Keep the thread loaded with the request, for showing it again without fetching it
+    pub(crate) fn cache_thread(&mut self, request: Request) {
+        self.thread_source = Some(request.clone());
+        self.cached_threads.insert(
+            request,
+            CachedThread {
+                posts: self.thread.items.clone(),
+                archive: self.thread_archive.clone(),
+                selected: self.selected_post().map(ThreadPost::no),
+            },
+        );
+    }
+
+    /// Update the cached thread list and thread with how they are left, selection included
+    pub(crate) fn remember(&mut self, thread_list: &ThreadList) {
+        let selected_thread = self.selected_thread_no().map(|no| no as usize);
+        if let Some(cached) = self
+            .threads_source
+            .clone()
+            .and_then(|request| self.cached_thread_list(&request))
+        {
+            cached.thread_list = thread_list.clone();
+            cached.selected = selected_thread;
+        }
+
+        let selected_post = self.selected_post().map(ThreadPost::no);
+        if let Some(cached) = self
+            .thread_source
+            .as_ref()
+            .and_then(|request| self.cached_threads.get(request))
+        {
+            cached.selected = selected_post;
+        }
+    }
+
+    fn cached_thread_list(&mut self, request: &Request) -> Option<&mut CachedThreads> {
+        self.cached_thread_lists
+            .get_mut(request.board()?)?
+            .get(request)
+    }
+
+    /// Request of the thread list last shown of the board, if it is still cached
+    pub(crate) fn board_request(&self, board: &str) -> Option<&Request> {
+        self.board_requests.get(board).filter(|request| {
+            self.cached_thread_lists
+                .get(board)
+                .is_some_and(|cached| cached.contains(request))
+        })
+    }
+
+    /// Show the cached thread list with the thread it was left on selected.
+    ///
+    /// Returns the page and mode it was shown with, `None` if it is not cached.
+    pub(crate) fn restore_threads(&mut self, request: &Request) -> Option<ThreadList> {
+        let cached = self.cached_thread_list(request)?;
+        let threads = cached.threads.clone();
+        let thread_list = cached.thread_list.clone();
+
+        self.pending_thread = cached.selected;
+        match thread_list.mode() {
+            ThreadListMode::Catalog(sort) => self.fill_catalog(threads, sort),
+            _ => self.fill_threads(threads),
+        }
+        self.threads_source = Some(request.clone());
+        if let Some(board) = request.board() {
+            self.board_requests
+                .insert(board.to_string(), request.clone());
+        }
+
+        Some(thread_list)
+    }
+
+    /// Show the cached thread with the post it was left on selected, `false` if it is not cached.
+    ///
+    /// A post set with `set_pending_post` is selected instead.
+    pub(crate) fn restore_thread(&mut self, request: &Request) -> bool {
+        let (Request::Thread { no, .. }, Some(cached)) =
+            (request, self.cached_threads.get(request))
+        else {
+            return false;
+        };
+        let posts = cached.posts.clone();
+        let archive = cached.archive.clone();
+        let selected = cached.selected;
+
+        let no = *no as usize;
+        let pending = self.pending_post.filter(|(thread, _)| *thread == no);
+        if let Some(post) = selected.filter(|_| pending.is_none()) {
+            self.set_pending_post(no, post);
+        }
+        self.fill_thread(posts, archive);
+        self.thread_source = Some(request.clone());
+
+        true
+    }
+
+    /// Fill threads from the catalog, which is always fetched in bump order
+    pub(crate) fn fill_catalog(&mut self, threads: Vec<Thread>, sort: CatalogSort) {
+        self.bump_order = threads
+            .iter()
+            .enumerate()
+            .filter_map(|(i, thread)| Some((thread.posts().first()?.no(), i)))
+            .collect();
+        self.fill_threads(threads);
+        self.sort_catalog(sort);
+    }
+
+    /// Sort catalog threads, keeping the selected thread selected
+    pub(crate) fn sort_catalog(&mut self, sort: CatalogSort) {
+        let selected_no = self.selected_thread_no();
+        let bump_order = &self.bump_order;
+
+        let key = |thread: &Thread| -> i64 {
+            let Some(op) = thread.posts().first() else {
+                return i64::MAX;
+            };
+
+            match sort {
+                CatalogSort::BumpOrder => *bump_order.get(&op.no()).unwrap_or(&usize::MAX) as i64,
+                CatalogSort::ReplyCount => -(op.replies() as i64),
+                CatalogSort::ImageCount => -(op.images() as i64),
+                CatalogSort::CreationDate => -(op.time() as i64),
+                CatalogSort::LastReply => -(thread.last_reply_time() as i64),
+            }
+        };
+        self.threads.items.sort_by_key(key);
+
+        if let Some(no) = selected_no {
+            let selected = self
+                .threads
+                .items
+                .iter()
+                .position(|thread| thread.posts().first().map(|op| op.no() as u64) == Some(no));
+            self.threads.state.select(selected);
+        }
+    }
+
+    /// Fill thread posts, `archive` is the name of the archive they were fetched from
+    pub(crate) fn fill_thread(&mut self, thread: Vec<ThreadPost>, archive: Option<String>) {
+        let board = self.selected_board().map(|board| board.board().to_string());
+
+        // a thread known only by its number gets its OP once loaded
+        if let (Some(op), Some(selected)) = (thread.first(), self.threads.state.selected()) {
+            if let Some(listed) = self.threads.items.get_mut(selected) {
+                let stub = listed
+                    .posts()
+                    .first()
+                    .is_some_and(|listed| listed.stub() && listed.no() == op.no());
+                if stub {
+                    *listed = Thread::new(vec![op.clone()]);
+                }
+            }
+        }
+
+        self.reply_graph = ReplyGraph::new(&thread, board.as_deref());
         self.thread = ItemLIst::new(thread);
+        self.thread_source = None;
+        self.thread_archive = archive;
+        self.quote_stack.clear();
+        self.reply_cycle = None;
+        self.previews.clear();
+        self.conversation = None;
+        self.collapsed_posts.clear();
+        self.arrange_thread();
+
+        let op_no = self.thread.items.first().map(ThreadPost::no);
+        if let Some((_, post)) = self
+            .pending_post
+            .filter(|(thread, _)| Some(*thread) == op_no)
+        {
+            self.pending_post = None;
+            if let Some(position) = self.post_index.get(&post) {
+                self.thread.state.select(Some(*position));
+            }
+        }
+    }
+
+    /// Select the post once the thread is loaded
+    pub(crate) fn set_pending_post(&mut self, thread: usize, post: usize) {
+        self.pending_post = Some((thread, post));
+    }
+
+    /// Order the posts for the chosen view, chronologically or as a tree, keeping the selected post selected
+    fn arrange_thread(&mut self) {
+        let selected = self.selected_post().map(ThreadPost::no);
+
+        self.thread.items.sort_by_key(ThreadPost::no);
+        self.tree.clear();
+        if self.tree_view {
+            let tree = self.reply_graph.tree(&self.thread.items);
+            let mut posts: Vec<Option<ThreadPost>> = std::mem::take(&mut self.thread.items)
+                .into_iter()
+                .map(Some)
+                .collect();
+            for (i, depth) in tree {
+                if let Some(post) = posts[i].take() {
+                    self.thread.items.push(post);
+                    self.tree.push((i, depth));
+                }
+            }
+        }
+        self.post_index = post_index(&self.thread.items);
+
+        if let Some(position) = selected.and_then(|no| self.post_index.get(&no)) {
+            self.thread.state.select(Some(*position));
+        }
+        self.refresh_hidden();
+    }
+
+    /// Hide the posts outside of the shown conversation and the replies of collapsed posts
+    fn refresh_hidden(&mut self) {
+        let conversation = self
+            .conversation
+            .map(|no| self.reply_graph.conversation(no));
+        let mut hidden = HashSet::new();
+        // depth of the collapsed post whose replies are being hidden
+        let mut collapsed_depth = None;
+
+        for (i, post) in self.thread.items.iter().enumerate() {
+            let depth = self.post_depth(i);
+            match collapsed_depth {
+                Some(collapsed) if depth > collapsed => {
+                    hidden.insert(i);
+                    continue;
+                }
+                _ => collapsed_depth = None,
+            }
+
+            if conversation
+                .as_ref()
+                .is_some_and(|conversation| !conversation.contains(&post.no()))
+            {
+                hidden.insert(i);
+            }
+            if self.tree_view && self.collapsed_posts.contains(&post.no()) {
+                collapsed_depth = Some(depth);
+            }
+        }
+
+        self.thread.set_hidden(hidden);
+        // the selection moves out of collapsed replies
+        if self.thread.state.selected().is_some() {
+            self.thread.advance_by(0);
+        }
+    }
+
+    /// Show the thread as a tree or in chronological order
+    pub(crate) fn toggle_tree_view(&mut self) {
+        self.tree_view ^= true;
+        // positions change with the order
+        self.quote_stack.clear();
+        self.reply_cycle = None;
+        self.arrange_thread();
+    }
+
+    /// Depth of the post at the position in the thread tree, 0 outside of the tree view
+    pub(crate) fn post_depth(&self, position: usize) -> usize {
+        self.tree.get(position).map_or(0, |(_, depth)| *depth)
+    }
+
+    /// Chronological position of the post at the position, which differs in the tree view
+    pub(crate) fn post_ordinal(&self, position: usize) -> usize {
+        self.tree
+            .get(position)
+            .map_or(position, |(ordinal, _)| *ordinal)
+    }
+
+    /// Collapse or expand the replies nested under the selected post in the tree view
+    pub(crate) fn toggle_collapsed(&mut self) {
+        if !self.tree_view {
+            return;
+        }
+        let Some(no) = self.selected_post().map(ThreadPost::no) else {
+            return;
+        };
+
+        if !self.collapsed_posts.remove(&no) {
+            self.collapsed_posts.insert(no);
+        }
+        self.refresh_hidden();
+    }
+
+    /// Number of replies hidden under the post at the position, if it is collapsed
+    pub(crate) fn collapsed_replies(&self, position: usize) -> usize {
+        let collapsed = self
+            .thread
+            .items
+            .get(position)
+            .is_some_and(|post| self.collapsed_posts.contains(&post.no()));
+        if !self.tree_view || !collapsed {
+            return 0;
+        }
+
+        let depth = self.post_depth(position);
+        (position + 1..self.thread.items.len())
+            .take_while(|i| self.post_depth(*i) > depth)
+            .count()
+    }
+
+    /// Posts of the opened thread, their position by post number
+    pub(crate) fn thread_posts(&self) -> &HashMap<usize, usize> {
+        &self.post_index
+    }
+
+    /// Quotes between the posts of the opened thread
+    pub(crate) fn reply_graph(&self) -> &ReplyGraph {
+        &self.reply_graph
+    }
+
+    /// Select the first post quoted by the selected post, if it is in the thread
+    pub(crate) fn follow_quote(&mut self) {
+        let Some(selected) = self.thread.state.selected() else {
+            return;
+        };
+        let Some(post) = self.thread.items.get(selected) else {
+            return;
+        };
+
+        let quoted = self
+            .reply_graph
+            .quotes(post.no())
+            .iter()
+            .filter_map(|no| self.post_index.get(no))
+            .find(|position| !self.thread.hidden(**position));
+        if let Some(&target) = quoted {
+            self.quote_stack.push(selected);
+            self.thread.state.select(Some(target));
+        }
+    }
+
+    /// Select the next reply to the post, `steps` replies further.
+    ///
+    /// Cycling continues from the last selected reply, as long as it stays selected.
+    pub(crate) fn cycle_replies(&mut self, steps: isize) {
+        let Some(selected) = self.thread.state.selected() else {
+            return;
+        };
+        let Some(no) = self.thread.items.get(selected).map(ThreadPost::no) else {
+            return;
+        };
+
+        // replies hidden by the conversation view are skipped
+        let replies = |origin| -> Vec<usize> {
+            self.reply_graph
+                .replies(origin)
+                .iter()
+                .filter_map(|no| self.post_index.get(no).copied())
+                .filter(|position| !self.thread.hidden(*position))
+                .collect()
+        };
+
+        let (origin, index) = match self.reply_cycle {
+            Some((origin, index)) if replies(origin).get(index) == Some(&selected) => {
+                (origin, index as isize + steps)
+            }
+            _ => (no, if steps > 0 { steps - 1 } else { steps }),
+        };
+
+        let replies = replies(origin);
+        if replies.is_empty() {
+            return;
+        }
+        let index = index.rem_euclid(replies.len() as isize) as usize;
+
+        if let Some(&target) = replies.get(index) {
+            if origin == no {
+                self.quote_stack.push(selected);
+            }
+            self.reply_cycle = Some((origin, index));
+            self.thread.state.select(Some(target));
+        }
+    }
+
+    /// Select the post the last quote was followed from, skipping posts hidden by the conversation view
+    pub(crate) fn quote_back(&mut self) {
+        while let Some(position) = self.quote_stack.pop() {
+            if position < self.thread.items.len() && !self.thread.hidden(position) {
+                self.thread.state.select(Some(position));
+                return;
+            }
+        }
+    }
+
+    /// Show only the conversation of the selected post, its position in the thread is kept
+    pub(crate) fn open_conversation(&mut self) {
+        let Some(no) = self.selected_post().map(ThreadPost::no) else {
+            return;
+        };
+
+        self.conversation = Some(no);
+        self.refresh_hidden();
+    }
+
+    /// Show the whole thread again, keeping the selected post selected
+    pub(crate) fn close_conversation(&mut self) {
+        self.conversation = None;
+        self.refresh_hidden();
+    }
+
+    /// Post whose conversation is shown
+    pub(crate) fn conversation(&self) -> Option<usize> {
+        self.conversation
+    }
+
+    /// Preview the posts quoted by the selected post, or by the post selected in the innermost preview
+    pub(crate) fn open_preview(&mut self) {
+        let no = match self.previews.last() {
+            Some(preview) => preview.selected(),
+            None => self.selected_post().map(ThreadPost::no),
+        };
+        let Some(no) = no else {
+            return;
+        };
+
+        let posts = self.reply_graph.quotes(no).to_vec();
+        if posts.is_empty() {
+            return;
+        }
+
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.previews.push(Preview { no, posts, state });
+    }
+
+    /// Close the innermost preview
+    pub(crate) fn close_preview(&mut self) {
+        self.previews.pop();
+    }
+
+    pub(crate) fn close_previews(&mut self) {
+        self.previews.clear();
+    }
+
+    pub(crate) fn previewing(&self) -> bool {
+        !self.previews.is_empty()
+    }
+
+    /// Open previews, the innermost last
+    pub(crate) fn previews(&self) -> &[Preview] {
+        &self.previews
+    }
+
+    /// Select another post of the innermost preview, wrapping around
+    pub(crate) fn advance_preview(&mut self, steps: isize) {
+        if let Some(preview) = self.previews.last_mut() {
+            let selected = preview.state.selected().unwrap_or(0) as isize + steps;
+            let selected = selected.rem_euclid(preview.posts.len() as isize) as usize;
+            preview.state.select(Some(selected));
+        }
+    }
+
+    /// Post of the opened thread, with its position in the thread
+    pub(crate) fn thread_post(&self, no: usize) -> Option<(usize, &ThreadPost)> {
+        let position = *self.post_index.get(&no)?;
+        Some((position, self.thread.items.get(position)?))
+    }
+
+    /// Links of the post to other threads and boards, as locations on the board of the post
+    fn cross_links(&self, post: &ThreadPost) -> Vec<Location> {
+        let Some(board) = self.selected_board().map(Board::board) else {
+            return vec![];
+        };
+        // replies know their thread, OPs are the thread
+        let thread_no = if post.resto() == 0 {
+            post.no()
+        } else {
+            post.resto()
+        };
+
+        post.links()
+            .into_iter()
+            .filter_map(|link| match link {
+                Link::Post(_) => None,
+                Link::Thread {
+                    board: link_board,
+                    thread,
+                    post,
+                } => {
+                    let link_board = link_board.as_deref().unwrap_or(board);
+                    let same_thread = link_board == board && thread == thread_no;
+                    (!same_thread).then(|| Location::thread(link_board, thread, post))
+                }
+                Link::Board(link_board) => Some(Location::board(&link_board)),
+            })
+            .collect()
+    }
+
+    /// List the links of the selected post of the panel to other threads and boards
+    pub(crate) fn open_link_menu(&mut self, selected_field: &SelectedField) {
+        let Some(post) = self.selected_post_in(selected_field) else {
+            return;
+        };
+
+        let locations = self.cross_links(post);
+        if locations.is_empty() {
+            return;
+        }
+
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.link_menu = Some(LinkMenu { locations, state });
+    }
+
+    pub(crate) fn close_link_menu(&mut self) {
+        self.link_menu = None;
+    }
+
+    pub(crate) fn link_menu(&self) -> Option<&LinkMenu> {
+        self.link_menu.as_ref()
+    }
+
+    /// Select another link of the menu, wrapping around
+    pub(crate) fn advance_link_menu(&mut self, steps: isize) {
+        if let Some(menu) = &mut self.link_menu {
+            let selected = menu.state.selected().unwrap_or(0) as isize + steps;
+            let selected = selected.rem_euclid(menu.locations.len() as isize) as usize;
+            menu.state.select(Some(selected));
+        }
+    }
+
+    /// Location of the link selected in the menu
+    pub(crate) fn selected_link(&self) -> Option<&Location> {
+        let menu = self.link_menu.as_ref()?;
+        menu.locations.get(menu.state.selected()?)
+    }
+
+    /// Select the board by name, `false` if it is not in the board list
+    pub(crate) fn select_board(&mut self, name: &str) -> bool {
+        let position = self
+            .boards
+            .items
+            .iter()
+            .position(|board| board.board() == name);
+        self.boards
+            .state
+            .select(position.or(self.boards.state.selected()));
+
+        position.is_some()
+    }
+
+    /// Where the app is, the opened thread or the thread list of the board, and the state of the panels
+    pub(crate) fn visit(
+        &self,
+        selected_field: &SelectedField,
+        thread_list: &ThreadList,
+    ) -> Option<Visit> {
+        let board = self.selected_board()?.board();
+        let selected_thread = self.selected_thread_no().map(|no| no as usize);
+        let location = match selected_thread.filter(|_| self.shown_state.thread) {
+            Some(no) => Location::thread(board, no, self.selected_post().map(ThreadPost::no)),
+            None => Location::board(board),
+        };
+
+        Some(Visit {
+            location,
+            view: Some(View {
+                selected_field: *selected_field,
+                shown_state: self.shown_state,
+                thread_list: thread_list.clone(),
+                boards: self.boards.state.clone(),
+                threads: self.threads.state.clone(),
+                thread: self.thread.state.clone(),
+                selected_thread,
+                tree_view: self.tree_view,
+            }),
+        })
+    }
+
+    /// Remember the current location in the history, before going somewhere else
+    pub(crate) fn leave(&mut self, selected_field: &SelectedField, thread_list: &ThreadList) {
+        self.remember(thread_list);
+        if let Some(visit) = self.visit(selected_field, thread_list) {
+            self.history.visit(visit);
+        }
+    }
+
+    pub(crate) fn history_mut(&mut self) -> &mut History<Visit> {
+        &mut self.history
+    }
+
+    /// Show the visited location as it was left, its board must be selected.
+    ///
+    /// Returns the panel to select and the request for the content that is not loaded anymore,
+    /// `threads_request` is the request for the thread list of the visit.
+    pub(crate) fn restore(
+        &mut self,
+        visit: &Visit,
+        threads_request: Request,
+    ) -> (SelectedField, Option<Request>) {
+        let location = &visit.location;
+        let view = visit.view.as_ref();
+        let board_info = self.shown_state.board_info;
+        let selected_field = match view {
+            Some(view) => {
+                self.shown_state = view.shown_state;
+                view.selected_field
+            }
+            None => {
+                let thread = location.thread.is_some();
+                self.shown_state = ShownState {
+                    board_list: !thread,
+                    thread_list: true,
+                    thread,
+                    board_info,
+                };
+                if thread {
+                    SelectedField::Thread
+                } else {
+                    SelectedField::ThreadList
+                }
+            }
+        };
+        self.shown_state.board_info = board_info;
+        if let Some(view) =
+            view.filter(|view| view.boards.selected() == self.boards.state.selected())
+        {
+            self.boards.state = view.boards.clone();
+        }
+        self.close_link_menu();
+        self.close_previews();
+
+        let threads_loaded = self.threads_source.as_ref() == Some(&threads_request)
+            || (self.shown_state.thread_list && self.restore_threads(&threads_request).is_some());
+        if threads_loaded {
+            if let Some(view) = view {
+                self.threads.state = view.threads.clone();
+            }
+        }
+
+        let Some(no) = location.thread else {
+            if threads_loaded || !self.shown_state.thread_list {
+                return (selected_field, None);
+            }
+
+            self.fill_threads(vec![]);
+            self.pending_thread = view.and_then(|view| view.selected_thread);
+            return (selected_field, Some(threads_request));
+        };
+
+        // threads are opened without the thread list, unless it is loaded and has the thread
+        let listed = self
+            .threads
+            .items
+            .iter()
+            .position(|thread| thread.posts().first().map(ThreadPost::no) == Some(no));
+        match listed.filter(|_| threads_loaded) {
+            Some(position) => self.threads.state.select(Some(position)),
+            None => {
+                self.fill_threads(vec![Thread::new(vec![ThreadPost::thread_stub(no)])]);
+                self.threads.state.select(Some(0));
+            }
+        }
+
+        let thread_request = Request::Thread {
+            board: location.board.clone(),
+            no: no as u64,
+        };
+        if self.thread_source.as_ref() == Some(&thread_request) {
+            if let Some(post) = location.post {
+                self.select_post(post, view);
+            }
+            return (selected_field, None);
+        }
+
+        if let Some(post) = location.post {
+            self.set_pending_post(no, post);
+        }
+        if self.restore_thread(&thread_request) {
+            return (selected_field, None);
+        }
+
+        self.fill_thread(vec![], None);
+        (selected_field, Some(thread_request))
+    }
+
+    /// Select the post by number, scrolled as in the view if the posts are still in the same order
+    fn select_post(&mut self, no: usize, view: Option<&View>) {
+        let Some(&position) = self.post_index.get(&no) else {
+            return;
+        };
+
+        let saved = view
+            .filter(|view| view.tree_view == self.tree_view)
+            .map(|view| &view.thread)
+            .filter(|saved| saved.selected() == Some(position));
+        match saved {
+            Some(saved) => self.thread.state = saved.clone(),
+            None => self.thread.state.select(Some(position)),
+        }
+        // a post in collapsed replies is not shown
+        if self.thread.hidden(position) {
+            self.thread.advance_by(0);
+        }
+    }
+
+    /// Reveal or hide spoilers of the post on the selected board
+    pub(crate) fn toggle_spoilers(&mut self, no: usize) {
+        if let Some(board) = self.selected_board().map(|board| board.board().to_string()) {
+            toggle_post(&mut self.revealed_spoilers, board, no);
+        }
+    }
+
+    pub(crate) fn spoilers_revealed(&self, no: usize) -> bool {
+        self.selected_board()
+            .and_then(|board| self.revealed_spoilers.get(board.board()))
+            .is_some_and(|posts| posts.contains(&no))
+    }
+
+    /// Expand or collapse the comment of the post on the selected board
+    pub(crate) fn toggle_expanded(&mut self, no: usize) {
+        if let Some(board) = self.selected_board().map(|board| board.board().to_string()) {
+            toggle_post(&mut self.expanded_posts, board, no);
+        }
+    }
+
+    pub(crate) fn post_expanded(&self, no: usize) -> bool {
+        self.selected_board()
+            .and_then(|board| self.expanded_posts.get(board.board()))
+            .is_some_and(|posts| posts.contains(&no))
+    }
+
+    pub(crate) fn open_reader(&mut self, no: usize) {
+        self.reader = Some(Reader { no, scroll: 0 });
     }
 
-    pub(crate) fn advance_idly(&self) {}
+    pub(crate) fn close_reader(&mut self) {
+        self.reader = None;
+    }
+
+    pub(crate) fn reading(&self) -> bool {
+        self.reader.is_some()
+    }
+
+    /// Post shown in the reader, looked up in the thread and then among the thread list OPs
+    pub(crate) fn reader_post(&self) -> Option<&ThreadPost> {
+        let no = self.reader.as_ref()?.no;
+
+        self.thread
+            .items
+            .iter()
+            .chain(self.threads.items.iter().filter_map(|t| t.posts().first()))
+            .find(|post| post.no() == no)
+    }
+
+    pub(crate) fn scroll_reader(&mut self, steps: isize) {
+        if let Some(reader) = &mut self.reader {
+            reader.scroll = reader.scroll.saturating_add_signed(steps as i16);
+        }
+    }
+
+    /// Scroll offset of the reader, kept within `max_scroll` lines
+    pub(crate) fn reader_scroll(&mut self, max_scroll: u16) -> u16 {
+        let Some(reader) = &mut self.reader else {
+            return 0;
+        };
+
+        reader.scroll = reader.scroll.min(max_scroll);
+        reader.scroll
+    }
+
+    pub(crate) fn advance_idly(&mut self) {
+        self.spinner = (self.spinner + 1) % SPINNER_FRAMES.len();
+        self.now = unix_now();
+    }
+
+    /// Unix time of the last tick
+    pub(crate) fn now(&self) -> u64 {
+        self.now
+    }
+
+    /// Current frame of the loading indicator shown in pane titles
+    pub(crate) fn spinner(&self) -> &'static str {
+        SPINNER_FRAMES[self.spinner]
+    }
 
     pub(crate) fn advance(&mut self, selected_field: &SelectedField, steps: isize) {
         match selected_field {
@@ -165,18 +1198,34 @@ impl App {
         }
     }
 
-    pub(crate) fn selected_board(&self) -> &Board {
-        &self.boards.items[self.boards.state.selected().unwrap_or(0)]
+    pub(crate) fn selected_board(&self) -> Option<&Board> {
+        self.boards
+            .items
+            .get(self.boards.state.selected().unwrap_or(0))
     }
 
-    pub(crate) fn selected_thread(&self) -> &Thread {
-        &self.threads.items[self.threads.state.selected().unwrap_or(0)]
+    pub(crate) fn selected_thread(&self) -> Option<&Thread> {
+        self.threads
+            .items
+            .get(self.threads.state.selected().unwrap_or(0))
+    }
+
+    pub(crate) fn selected_thread_no(&self) -> Option<u64> {
+        self.selected_thread()
+            .and_then(|thread| thread.posts().first())
+            .map(|post| post.no() as u64)
     }
 
     pub(crate) fn selected_thread_description(&self) -> String {
         if let Some(post_i) = self.threads.state.selected() {
-            let thread = &self.threads.items[post_i];
-            let post = thread.posts().first().unwrap();
+            let Some(post) = self
+                .threads
+                .items
+                .get(post_i)
+                .and_then(|thread| thread.posts().first())
+            else {
+                return "".to_string();
+            };
             let title = format_html(post.sub());
             let title = if title.is_empty() {
                 "".to_string()
@@ -184,14 +1233,83 @@ impl App {
                 format!("\"{}\" ", title)
             };
 
-            format!("{} {}replies: {} ", post.no(), title, post.replies())
+            let dead = if self.threads.items[post_i].dead() {
+                "(dead) "
+            } else if self.thread_archived() {
+                "(archived, read-only) "
+            } else {
+                ""
+            };
+
+            let source = match &self.thread_archive {
+                Some(archive) => format!("(from archive {}) ", archive),
+                None => "".to_string(),
+            };
+
+            let conversation = match self.conversation {
+                Some(no) => format!("(conversation of >>{}) ", no),
+                None => "".to_string(),
+            };
+            let tree = if self.tree_view { "(tree) " } else { "" };
+
+            format!(
+                "{} {}replies: {} {}{}{}{}",
+                post.no(),
+                title,
+                post.replies(),
+                dead,
+                source,
+                conversation,
+                tree
+            )
         } else {
             "".to_string()
         }
     }
 
-    pub(crate) fn selected_post(&self) -> &ThreadPost {
-        &self.thread.items[self.thread.state.selected().unwrap()]
+    /// Mark the thread as pruned or deleted, if it is still in the thread list
+    pub(crate) fn mark_thread_dead(&mut self, no: u64) {
+        let board = self.selected_board().map(|board| board.board().to_string());
+        // the thread stays dead when its thread list is shown again from the cache
+        let cached = board
+            .and_then(|board| self.cached_thread_lists.get_mut(&board))
+            .into_iter()
+            .flat_map(Lru::values_mut)
+            .map(|cached| &mut cached.threads);
+
+        for threads in std::iter::once(&mut self.threads.items).chain(cached) {
+            let thread = threads.iter_mut().find(|thread| {
+                thread
+                    .posts()
+                    .first()
+                    .is_some_and(|post| post.no() as u64 == no)
+            });
+
+            if let Some(thread) = thread {
+                thread.set_dead();
+            }
+        }
+    }
+
+    /// Opened thread is archived and will not change anymore
+    pub(crate) fn thread_archived(&self) -> bool {
+        self.thread
+            .items
+            .first()
+            .is_some_and(|post| post.archived() == 1)
+    }
+
+    pub(crate) fn selected_post(&self) -> Option<&ThreadPost> {
+        self.thread.items.get(self.thread.state.selected()?)
+    }
+
+    /// Selected post of the panel, the OP of the selected thread in the thread list
+    pub(crate) fn selected_post_in(&self, selected_field: &SelectedField) -> Option<&ThreadPost> {
+        match selected_field {
+            SelectedField::BoardList => None,
+            SelectedField::ThreadList => self.selected_thread()?.posts().first(),
+            SelectedField::Thread => self.selected_post(),
+        }
     }
 
     pub(crate) fn set_shown_board_list(&mut self, shown: bool) {
@@ -206,6 +1324,18 @@ impl App {
         self.shown_state.thread = shown;
     }
 
+    pub(crate) fn toggle_shown_board_info(&mut self) {
+        self.shown_state.board_info ^= true;
+    }
+
+    pub(crate) fn set_shown_board_info(&mut self, shown: bool) {
+        self.shown_state.board_info = shown;
+    }
+
+    pub(crate) fn shown_board_info(&self) -> bool {
+        self.shown_state.board_info
+    }
+
     pub(crate) fn toggle_shown_board_list(&mut self) {
         self.shown_state.board_list ^= true;
     }
@@ -240,35 +1370,44 @@ impl App {
         &mut self.help_bar
     }
 
-    pub(crate) fn url_boards(&self, url_provider: &dyn ContentUrlProvider) -> String {
-        url_provider.url_board(self.selected_board().board())
+    pub(crate) fn status(&self) -> Option<&String> {
+        self.status.as_ref()
+    }
+
+    pub(crate) fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    pub(crate) fn clear_status(&mut self) {
+        self.status = None;
+    }
+
+    pub(crate) fn url_boards(&self, url_provider: &dyn ContentUrlProvider) -> Option<String> {
+        Some(url_provider.url_board(self.selected_board()?.board()))
     }
 
-    pub(crate) fn url_threads(&self, url_provider: &dyn ContentUrlProvider) -> String {
-        url_provider.url_thread(
-            self.selected_board().board(),
-            self.selected_thread().posts().first().unwrap().no() as u64,
-        )
+    pub(crate) fn url_threads(&self, url_provider: &dyn ContentUrlProvider) -> Option<String> {
+        Some(url_provider.url_thread(self.selected_board()?.board(), self.selected_thread_no()?))
     }
 
-    pub(crate) fn url_thread(&self, url_provider: &dyn ContentUrlProvider) -> String {
-        url_provider.url_thread_post(
-            self.selected_board().board(),
-            self.selected_thread().posts().first().unwrap().no() as u64,
-            self.selected_post().no() as u64,
-        )
+    pub(crate) fn url_thread(&self, url_provider: &dyn ContentUrlProvider) -> Option<String> {
+        Some(url_provider.url_thread_post(
+            self.selected_board()?.board(),
+            self.selected_thread_no()?,
+            self.selected_post()?.no() as u64,
+        ))
     }
 
     pub(crate) fn media_url_threads(
         &self,
         url_provider: &dyn ContentUrlProvider,
     ) -> Option<String> {
-        let post = self.selected_thread().posts().first().unwrap();
+        let post = self.selected_thread()?.posts().first()?;
         self.media_url(post, url_provider)
     }
 
     pub(crate) fn media_url_thread(&self, url_provider: &dyn ContentUrlProvider) -> Option<String> {
-        let post = self.selected_post();
+        let post = self.selected_post()?;
         self.media_url(post, url_provider)
     }
 
@@ -277,23 +1416,38 @@ impl App {
         post: &ThreadPost,
         url_provider: &dyn ContentUrlProvider,
     ) -> Option<String> {
-        if post.tim().is_none() || post.ext().is_none() {
-            return None;
+        if let Some(url) = post.file_url() {
+            return Some(url.to_string());
         }
 
-        let url = url_provider.url_file(
-            self.selected_board().board(),
-            format!(
-                "{}{}",
-                post.tim().as_ref().unwrap(),
-                post.ext().as_ref().unwrap()
-            ),
-        );
+        let url = url_provider.url_file(self.selected_board()?.board(), post.media_filename()?);
 
         Some(url)
     }
 }
 
+const SPINNER_FRAMES: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// Thread lists kept for each board, enough for a few pages, the catalog and the archive
+const CACHED_THREAD_LISTS: usize = 4;
+
+/// Threads kept for returning to them without fetching them again
+const CACHED_THREADS: usize = 16;
+
+fn post_index(posts: &[ThreadPost]) -> HashMap<usize, usize> {
+    posts
+        .iter()
+        .enumerate()
+        .map(|(i, post)| (post.no(), i))
+        .collect()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
 pub(crate) struct ScreenShare {
     board_list: u16,
     thread_list: u16,
@@ -322,15 +1476,118 @@ impl ScreenShare {
     }
 }
 
+/// Reader overlay, showing a single post in full
+struct Reader {
+    /// Number of the post
+    no: usize,
+    /// Lines scrolled past
+    scroll: u16,
+}
+
+/// Popup previewing the posts quoted by a post, without moving the selection
+pub(crate) struct Preview {
+    /// Number of the quoting post
+    no: usize,
+    /// Numbers of the quoted posts
+    posts: Vec<usize>,
+    pub(crate) state: ListState,
+}
+
+impl Preview {
+    pub(crate) fn no(&self) -> usize {
+        self.no
+    }
+
+    pub(crate) fn posts(&self) -> &[usize] {
+        &self.posts
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.posts.get(self.state.selected()?).copied()
+    }
+}
+
+/// Menu of the links of a post to other threads and boards
+pub(crate) struct LinkMenu {
+    locations: Vec<Location>,
+    pub(crate) state: ListState,
+}
+
+impl LinkMenu {
+    pub(crate) fn locations(&self) -> &[Location] {
+        &self.locations
+    }
+}
+
+/// Location in the history, with the state of the panels it was left in
+#[derive(Clone)]
+pub(crate) struct Visit {
+    pub(crate) location: Location,
+    /// `None` for locations that were not visited yet, such as link targets
+    view: Option<View>,
+}
+
+impl Visit {
+    pub(crate) fn new(location: Location) -> Self {
+        Self {
+            location,
+            view: None,
+        }
+    }
+
+    /// Thread list as it was left
+    pub(crate) fn thread_list(&self) -> Option<&ThreadList> {
+        Some(&self.view.as_ref()?.thread_list)
+    }
+}
+
+/// State of the panels when a location was left
+#[derive(Clone)]
+struct View {
+    selected_field: SelectedField,
+    shown_state: ShownState,
+    thread_list: ThreadList,
+    /// Selection and scroll offset of each list
+    boards: ListState,
+    threads: ListState,
+    thread: ListState,
+    /// Selected thread of the thread list, by number
+    selected_thread: Option<usize>,
+    /// Thread was shown as a tree, with posts in another order
+    tree_view: bool,
+}
+
+/// Fetched thread list, as it was last left
+struct CachedThreads {
+    threads: Vec<Thread>,
+    /// Page and mode the thread list was shown in
+    thread_list: ThreadList,
+    /// Selected thread, by number
+    selected: Option<usize>,
+}
+
+/// Fetched thread, as it was last left
+struct CachedThread {
+    posts: Vec<ThreadPost>,
+    /// Name of the archive the thread was fetched from
+    archive: Option<String>,
+    /// Selected post, by number
+    selected: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
 struct ShownState {
     board_list: bool,
     thread_list: bool,
     thread: bool,
+    board_info: bool,
 }
 
 pub(crate) struct ItemLIst<T> {
     pub(crate) state: ListState,
     pub(crate) items: Vec<T>,
+    /// Positions of the items left out of the list, they are skipped when moving
+    hidden: HashSet<usize>,
 }
 
 pub(crate) struct HelpBar {
@@ -355,6 +1612,11 @@ impl HelpBar {
     pub(crate) fn text(&self) -> &String {
         &self.text
     }
+
+    /// Lines taken by the help bar, its title included
+    pub(crate) fn height(&self) -> u16 {
+        self.text.lines().count() as u16 + 1
+    }
 }
 
 impl<T> ItemLIst<T> {
@@ -362,27 +1624,643 @@ impl<T> ItemLIst<T> {
         ItemLIst {
             state: ListState::default(),
             items,
+            hidden: HashSet::new(),
         }
     }
 
+    /// Move the selection by `steps` shown items, wrapping around at the ends
     pub(crate) fn advance_by(&mut self, steps: isize) {
-        let selected = match self.state.selected() {
+        let shown: Vec<usize> = (0..self.items.len())
+            .filter(|i| !self.hidden.contains(i))
+            .collect();
+        if shown.is_empty() {
+            self.state.select(Some(0));
+            return;
+        }
+
+        // a hidden selection counts as the first shown item after it
+        let current = self
+            .state
+            .selected()
+            .map(|selected| shown.partition_point(|&i| i < selected));
+
+        let selected = match current {
             Some(selected) => {
-                if selected as isize >= self.items.len() as isize - steps {
+                if selected as isize >= shown.len() as isize - steps {
                     0_isize
                 } else if selected == 0 && steps < 0 {
-                    self.items.len() as isize - 1
+                    shown.len() as isize - 1
                 } else {
-                    selected as isize + steps
+                    (selected as isize + steps).max(0)
                 }
             }
             None => 0,
         };
 
-        self.state.select(Some(selected as usize));
+        self.state.select(Some(shown[selected as usize]));
+    }
+
+    /// Hide the items at the positions, showing all others
+    pub(crate) fn set_hidden(&mut self, hidden: HashSet<usize>) {
+        self.hidden = hidden;
+    }
+
+    pub(crate) fn hidden(&self, position: usize) -> bool {
+        self.hidden.contains(&position)
     }
 
     pub(crate) fn _unselect(&mut self) {
         self.state.select(None);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{post, quote, reply};
+
+    fn board(name: &str, ws_board: u8) -> Board {
+        serde_json::from_value(serde_json::json!({
+            "board": name,
+            "title": name,
+            "meta_description": "",
+            "per_page": 15,
+            "pages": 10,
+            "bump_limit": 300,
+            "ws_board": ws_board,
+        }))
+        .unwrap()
+    }
+
+    /// Reply that knows its thread, as posts fetched with their thread do
+    fn thread_reply(thread: usize, no: usize, com: &str) -> ThreadPost {
+        serde_json::from_value(serde_json::json!({ "no": no, "resto": thread, "com": com }))
+            .unwrap()
+    }
+
+    /// App with the default keybinds and nothing loaded
+    fn app() -> App {
+        let keybinds = Keybinds::parse_from_file("").unwrap();
+        App::new(vec![], vec![], vec![], &keybinds)
+    }
+
+    /// Selected post of the thread, by number
+    fn selected(app: &App) -> Option<usize> {
+        app.selected_post().map(ThreadPost::no)
+    }
+
+    fn board_names(app: &App) -> Vec<&str> {
+        app.boards.items.iter().map(|board| board.board()).collect()
+    }
+
+    #[test]
+    fn test_toggle_nsfw() {
+        let mut app = app();
+        app.fill_boards(vec![
+            board("a", 1),
+            board("b", 0),
+            board("c", 1),
+            board("d", 0),
+        ]);
+        app.boards.state.select(Some(2));
+
+        app.toggle_nsfw();
+        assert!(!app.show_nsfw());
+        assert_eq!(board_names(&app), vec!["a", "c"]);
+        assert_eq!(app.selected_board().map(Board::board), Some("c"));
+
+        // refetched boards stay filtered
+        app.fill_boards(vec![board("a", 1), board("b", 0), board("c", 1)]);
+        assert_eq!(board_names(&app), vec!["a", "c"]);
+
+        // hidden boards return to their place
+        app.boards.state.select(Some(1));
+        app.toggle_nsfw();
+        assert_eq!(board_names(&app), vec!["a", "b", "c"]);
+        assert_eq!(app.selected_board().map(Board::board), Some("c"));
+    }
+
+    #[test]
+    fn test_spoilers_per_board() {
+        let mut app = app();
+        app.fill_boards(vec![board("g", 1), board("sci", 1)]);
+        app.boards.state.select(Some(0));
+
+        app.toggle_spoilers(5);
+        assert!(app.spoilers_revealed(5));
+
+        // the same number on another board is another post
+        app.boards.state.select(Some(1));
+        assert!(!app.spoilers_revealed(5));
+
+        app.boards.state.select(Some(0));
+        app.toggle_spoilers(5);
+        assert!(!app.spoilers_revealed(5));
+    }
+
+    #[test]
+    fn test_expanded_per_board() {
+        let mut app = app();
+        app.fill_boards(vec![board("g", 1), board("sci", 1)]);
+        app.boards.state.select(Some(1));
+
+        app.toggle_expanded(5);
+        assert!(app.post_expanded(5));
+
+        app.boards.state.select(Some(0));
+        assert!(!app.post_expanded(5));
+    }
+
+    #[test]
+    fn test_reader() {
+        let keybinds = Keybinds::parse_from_file("").unwrap();
+        let mut app = App::new(
+            vec![],
+            vec![Thread::new(vec![post(1), post(2)])],
+            vec![post(10), post(11)],
+            &keybinds,
+        );
+        assert!(app.reader_post().is_none());
+
+        app.open_reader(11);
+        assert_eq!(app.reader_post().map(ThreadPost::no), Some(11));
+
+        // OPs of the thread list, replies are not shown there
+        app.open_reader(1);
+        assert_eq!(app.reader_post().map(ThreadPost::no), Some(1));
+        app.open_reader(2);
+        assert!(app.reading() && app.reader_post().is_none());
+
+        app.scroll_reader(-5);
+        assert_eq!(app.reader_scroll(10), 0);
+        app.scroll_reader(15);
+        assert_eq!(app.reader_scroll(10), 10);
+        app.scroll_reader(-3);
+        assert_eq!(app.reader_scroll(10), 7);
+
+        app.close_reader();
+        assert!(!app.reading());
+    }
+
+    #[test]
+    fn test_follow_quote() {
+        let mut app = app();
+        app.fill_thread(
+            vec![
+                post(10),
+                reply(11, r##"<a href="#p99" class="quotelink">&gt;&gt;99</a>"##),
+                reply(
+                    12,
+                    r##"<a href="/g/thread/5#p5" class="quotelink">&gt;&gt;5</a>
+                    <a href="#p99" class="quotelink">&gt;&gt;99</a>
+                    <a href="thread/10#p11" class="quotelink">&gt;&gt;11</a>"##,
+                ),
+            ],
+            None,
+        );
+
+        // links to other threads and missing posts are skipped
+        app.thread.state.select(Some(2));
+        app.follow_quote();
+        assert_eq!(selected(&app), Some(11));
+
+        // nothing to follow
+        app.follow_quote();
+        assert_eq!(selected(&app), Some(11));
+
+        app.quote_back();
+        assert_eq!(selected(&app), Some(12));
+        app.quote_back();
+        assert_eq!(selected(&app), Some(12));
+    }
+
+    #[test]
+    fn test_cycle_replies() {
+        let mut app = app();
+        app.fill_thread(
+            vec![
+                post(10),
+                reply(11, &quote(10)),
+                post(12),
+                reply(13, &quote(10)),
+                reply(14, r##"<a href="#p11" class="quotelink">&gt;&gt;11</a>"##),
+            ],
+            None,
+        );
+
+        app.thread.state.select(Some(0));
+        app.cycle_replies(1);
+        assert_eq!(selected(&app), Some(11));
+        app.cycle_replies(1);
+        assert_eq!(selected(&app), Some(13));
+        app.cycle_replies(1);
+        assert_eq!(selected(&app), Some(11));
+        app.cycle_replies(-1);
+        assert_eq!(selected(&app), Some(13));
+
+        // moving away starts from the selected post
+        app.thread.state.select(Some(1));
+        app.cycle_replies(1);
+        assert_eq!(selected(&app), Some(14));
+
+        // no replies
+        app.cycle_replies(1);
+        assert_eq!(selected(&app), Some(14));
+
+        app.quote_back();
+        assert_eq!(selected(&app), Some(11));
+        app.quote_back();
+        assert_eq!(selected(&app), Some(10));
+
+        app.cycle_replies(-1);
+        assert_eq!(selected(&app), Some(13));
+    }
+
+    #[test]
+    fn test_conversation() {
+        let mut app = app();
+        app.fill_thread(
+            vec![
+                post(10),
+                reply(11, &quote(10)),
+                reply(12, &quote(10)),
+                reply(13, &quote(11)),
+                post(14),
+                reply(15, &(quote(13) + &quote(12))),
+            ],
+            None,
+        );
+
+        app.thread.state.select(Some(3));
+        app.open_conversation();
+        assert_eq!(app.conversation(), Some(13));
+        let hidden: Vec<usize> = (0..6).filter(|i| app.thread.hidden(*i)).collect();
+        assert_eq!(hidden, vec![2, 4]);
+
+        // moving skips the posts outside of the conversation
+        app.advance(&SelectedField::Thread, 1);
+        assert_eq!(selected(&app), Some(15));
+        app.advance(&SelectedField::Thread, 1);
+        assert_eq!(selected(&app), Some(10));
+        app.advance(&SelectedField::Thread, -1);
+        assert_eq!(selected(&app), Some(15));
+
+        // quotes of posts outside of the conversation are skipped
+        app.follow_quote();
+        assert_eq!(selected(&app), Some(13));
+        app.thread.state.select(Some(0));
+        app.cycle_replies(1);
+        assert_eq!(selected(&app), Some(11));
+        app.cycle_replies(1);
+        assert_eq!(selected(&app), Some(11));
+
+        app.close_conversation();
+        assert_eq!(app.conversation(), None);
+        assert_eq!(selected(&app), Some(11));
+        app.advance(&SelectedField::Thread, 1);
+        assert_eq!(selected(&app), Some(12));
+    }
+
+    #[test]
+    fn test_tree_view() {
+        let mut app = app();
+        app.fill_thread(
+            vec![
+                post(10),
+                reply(11, &quote(10)),
+                post(12),
+                reply(13, &quote(11)),
+                reply(14, &quote(13)),
+                reply(15, &quote(10)),
+            ],
+            None,
+        );
+        let numbers =
+            |app: &App| -> Vec<usize> { app.thread.items.iter().map(ThreadPost::no).collect() };
+
+        app.thread.state.select(Some(3));
+        app.toggle_tree_view();
+        assert_eq!(numbers(&app), vec![10, 11, 13, 14, 15, 12]);
+        let depths: Vec<usize> = (0..6).map(|i| app.post_depth(i)).collect();
+        assert_eq!(depths, vec![0, 1, 2, 3, 1, 0]);
+        assert_eq!(app.post_ordinal(5), 2);
+        assert_eq!(selected(&app), Some(13));
+
+        // quotes are followed to the new positions
+        app.follow_quote();
+        assert_eq!(selected(&app), Some(11));
+
+        // moving skips collapsed replies
+        app.toggle_collapsed();
+        assert_eq!(app.collapsed_replies(1), 2);
+        app.advance(&SelectedField::Thread, 1);
+        assert_eq!(selected(&app), Some(15));
+        app.advance(&SelectedField::Thread, -1);
+        assert_eq!(selected(&app), Some(11));
+
+        app.toggle_tree_view();
+        assert_eq!(numbers(&app), vec![10, 11, 12, 13, 14, 15]);
+        assert_eq!(app.post_depth(3), 0);
+        assert_eq!(app.collapsed_replies(1), 0);
+        app.advance(&SelectedField::Thread, 1);
+        assert_eq!(selected(&app), Some(12));
+
+        // the selection moves out of the replies of a collapsed post
+        app.thread.state.select(Some(4));
+        app.toggle_tree_view();
+        assert_eq!(selected(&app), Some(15));
+    }
+
+    #[test]
+    fn test_link_menu() {
+        let mut app = app();
+        app.fill_boards(vec![board("g", 1), board("sci", 1)]);
+        app.boards.state.select(Some(0));
+        app.fill_thread(
+            vec![
+                post(10),
+                thread_reply(
+                    10,
+                    11,
+                    r##"<a href="#p10" class="quotelink">&gt;&gt;10</a>
+                    <a href="/g/thread/10#p10" class="quotelink">&gt;&gt;10</a>
+                    <a href="/g/thread/5#p6" class="quotelink">&gt;&gt;6</a>
+                    <a href="thread/7" class="quotelink">&gt;&gt;7</a>
+                    <a href="//boards.4chan.org/sci/" class="quotelink">&gt;&gt;&gt;/sci/</a>
+                    &gt;&gt;&gt;/v/8"##,
+                ),
+            ],
+            None,
+        );
+        let links = |app: &App| -> Vec<String> {
+            app.link_menu()
+                .map(|menu| menu.locations().iter().map(Location::to_string).collect())
+                .unwrap_or_default()
+        };
+
+        app.thread.state.select(Some(0));
+        app.open_link_menu(&SelectedField::Thread);
+        assert!(app.link_menu().is_none());
+
+        // links within the thread are left out
+        app.thread.state.select(Some(1));
+        app.open_link_menu(&SelectedField::Thread);
+        assert_eq!(
+            links(&app),
+            vec!["/g/thread/5#p6", "/g/thread/7", "/sci/", "/v/thread/8"]
+        );
+
+        app.advance_link_menu(-1);
+        assert_eq!(app.selected_link(), Some(&Location::thread("v", 8, None)));
+        app.advance_link_menu(2);
+        assert_eq!(app.selected_link(), Some(&Location::thread("g", 7, None)));
+
+        app.close_link_menu();
+        assert!(app.link_menu().is_none());
+
+        assert!(app.select_board("sci"));
+        assert!(!app.select_board("v"));
+        assert_eq!(app.selected_board().map(Board::board), Some("sci"));
+    }
+
+    #[test]
+    fn test_pending_post() {
+        let mut app = app();
+        app.fill_boards(vec![board("g", 1)]);
+        app.boards.state.select(Some(0));
+        app.fill_threads(vec![Thread::new(vec![ThreadPost::thread_stub(10)])]);
+        app.threads.state.select(Some(0));
+        app.set_pending_post(10, 12);
+
+        // posts of another thread
+        app.fill_thread(vec![post(20), post(12)], None);
+        assert!(app.selected_post().is_none());
+
+        app.fill_thread(vec![post(10), post(11), post(12)], None);
+        assert_eq!(selected(&app), Some(12));
+
+        // the stub in the thread list is replaced by the loaded OP
+        assert!(!app.selected_thread().unwrap().posts()[0].stub());
+        assert_eq!(app.selected_thread_no(), Some(10));
+    }
+
+    #[test]
+    fn test_restore_visit() {
+        let mut app = app();
+        let thread_list = ThreadList::new();
+        let threads_request = Request::Threads {
+            board: "g".to_string(),
+            page: 1,
+        };
+        let thread_request = Request::Thread {
+            board: "g".to_string(),
+            no: 2,
+        };
+        let threads = || vec![Thread::new(vec![post(1)]), Thread::new(vec![post(2)])];
+
+        app.fill_boards(vec![board("g", 1), board("sci", 1)]);
+        app.boards.state.select(Some(0));
+        app.set_shown_thread_list(true);
+        app.fill_threads(threads());
+        app.cache_threads(threads_request.clone(), &thread_list);
+        app.threads.state.select(Some(1));
+        app.leave(&SelectedField::ThreadList, &thread_list);
+
+        app.set_shown_thread(true);
+        app.fill_thread(vec![post(2), post(3)], None);
+        app.cache_thread(thread_request.clone());
+        app.thread.state.select(Some(1));
+        let in_thread = app.visit(&SelectedField::Thread, &thread_list).unwrap();
+        assert_eq!(in_thread.location, Location::thread("g", 2, Some(3)));
+
+        // both are still loaded
+        let visit = app.history_mut().back(in_thread).unwrap();
+        let (field, request) = app.restore(&visit, threads_request.clone());
+        assert!(matches!(field, SelectedField::ThreadList) && request.is_none());
+        assert!(!app.shown_thread());
+        assert_eq!(app.selected_thread_no(), Some(2));
+
+        let current = app.visit(&SelectedField::ThreadList, &thread_list).unwrap();
+        let visit = app.history_mut().forward(current).unwrap();
+        let (field, request) = app.restore(&visit, threads_request.clone());
+        assert!(matches!(field, SelectedField::Thread) && request.is_none());
+        assert!(app.shown_thread());
+        assert_eq!(selected(&app), Some(3));
+
+        // another thread was loaded since, the thread is shown from the cache
+        app.fill_thread(vec![post(7)], None);
+        let (_, request) = app.restore(&visit, threads_request.clone());
+        assert_eq!(request, None);
+        assert_eq!(selected(&app), Some(3));
+
+        // another thread list was loaded since
+        let visit = app.history_mut().back(visit).unwrap();
+        app.fill_threads(vec![]);
+        let (_, request) = app.restore(&visit, threads_request.clone());
+        assert_eq!(request, None);
+        assert_eq!(app.selected_thread_no(), Some(2));
+
+        // a thread that was never loaded is fetched
+        let visit = Visit::new(Location::thread("g", 5, Some(6)));
+        let (_, request) = app.restore(&visit, threads_request);
+        let thread_request = Request::Thread {
+            board: "g".to_string(),
+            no: 5,
+        };
+        assert_eq!(request, Some(thread_request));
+        app.fill_thread(vec![post(5), post(6)], None);
+        assert_eq!(selected(&app), Some(6));
+    }
+
+    #[test]
+    fn test_restore_rearranged_thread() {
+        let mut app = app();
+        let thread_list = ThreadList::new();
+        let threads_request = Request::Threads {
+            board: "g".to_string(),
+            page: 1,
+        };
+        app.fill_boards(vec![board("g", 1)]);
+        app.boards.state.select(Some(0));
+        app.set_shown_thread(true);
+        app.fill_threads(vec![Thread::new(vec![post(1)])]);
+        app.threads.state.select(Some(0));
+        app.fill_thread(
+            vec![post(1), reply(2, &quote(1)), post(3), reply(4, &quote(2))],
+            None,
+        );
+        app.cache_thread(Request::Thread {
+            board: "g".to_string(),
+            no: 1,
+        });
+        app.thread.state.select(Some(3));
+        let visit = app.visit(&SelectedField::Thread, &thread_list).unwrap();
+
+        // the post moved in the tree
+        app.toggle_tree_view();
+        app.thread.state.select(Some(0));
+        app.restore(&visit, threads_request.clone());
+        assert_eq!(selected(&app), Some(4));
+
+        // the post is in collapsed replies
+        app.thread.state.select(Some(1));
+        app.toggle_collapsed();
+        app.restore(&visit, threads_request);
+        let position = app.thread.state.selected().unwrap();
+        assert!(!app.thread.hidden(position));
+        assert_eq!(selected(&app), Some(3));
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut app = app();
+        let request = |page| Request::Threads {
+            board: "g".to_string(),
+            page,
+        };
+        let thread_request = Request::Thread {
+            board: "g".to_string(),
+            no: 1,
+        };
+        let mut thread_list = ThreadList::new();
+        app.fill_boards(vec![board("g", 1)]);
+        app.boards.state.select(Some(0));
+
+        assert!(app.restore_threads(&request(1)).is_none());
+        assert!(!app.restore_thread(&thread_request));
+
+        app.fill_threads(vec![Thread::new(vec![post(1)]), Thread::new(vec![post(2)])]);
+        app.cache_threads(request(1), &thread_list);
+        app.threads.state.select(Some(1));
+        thread_list.set_description("Technology");
+        app.leave(&SelectedField::ThreadList, &thread_list);
+        assert_eq!(app.board_request("g"), Some(&request(1)));
+
+        app.fill_thread(vec![post(1), post(3), post(4)], None);
+        app.cache_thread(thread_request.clone());
+        app.thread.state.select(Some(2));
+
+        app.fill_threads(vec![Thread::new(vec![post(8)])]);
+        app.cache_threads(request(2), &ThreadList::new());
+        assert_eq!(app.board_request("g"), Some(&request(2)));
+        app.remember(&thread_list);
+
+        // shown as it was left
+        let cached = app.restore_threads(&request(1)).unwrap();
+        assert_eq!(cached.description(), "Technology");
+        assert_eq!(app.selected_thread_no(), Some(2));
+        assert_eq!(app.board_request("g"), Some(&request(1)));
+
+        app.fill_thread(vec![], None);
+        assert!(app.restore_thread(&thread_request));
+        assert_eq!(selected(&app), Some(4));
+
+        // a post from a link is selected instead
+        app.set_pending_post(1, 3);
+        assert!(app.restore_thread(&thread_request));
+        assert_eq!(selected(&app), Some(3));
+
+        // dead threads stay dead in the cached thread list
+        app.mark_thread_dead(2);
+        app.restore_threads(&request(2));
+        app.restore_threads(&request(1));
+        assert!(app.threads.items[1].dead());
+
+        // the least recently shown thread lists are dropped
+        for page in 2..=5 {
+            app.cache_threads(request(page), &ThreadList::new());
+        }
+        assert!(app.restore_threads(&request(1)).is_none());
+        app.board_requests.insert("g".to_string(), request(1));
+        assert_eq!(app.board_request("g"), None);
+    }
+
+    #[test]
+    fn test_previews() {
+        let mut app = app();
+        app.fill_thread(
+            vec![
+                post(10),
+                reply(11, &quote(10)),
+                reply(12, &(quote(11) + &quote(10))),
+            ],
+            None,
+        );
+        let preview_posts = |app: &App| -> Vec<Vec<usize>> {
+            app.previews()
+                .iter()
+                .map(|preview| preview.posts().to_vec())
+                .collect()
+        };
+
+        // nothing quoted
+        app.thread.state.select(Some(0));
+        app.open_preview();
+        assert!(!app.previewing());
+
+        app.thread.state.select(Some(2));
+        app.open_preview();
+        assert_eq!(preview_posts(&app), vec![vec![11, 10]]);
+
+        // nested preview of the post selected in the preview
+        app.open_preview();
+        assert_eq!(preview_posts(&app), vec![vec![11, 10], vec![10]]);
+        app.open_preview();
+        assert_eq!(app.previews().len(), 2);
+
+        app.close_preview();
+        app.advance_preview(1);
+        app.open_preview();
+        assert_eq!(app.previews().len(), 1);
+        app.advance_preview(-1);
+        app.open_preview();
+        assert_eq!(preview_posts(&app), vec![vec![11, 10], vec![10]]);
+        assert_eq!(app.previews()[1].no(), 11);
+
+        // the selection stays on the quoting post
+        assert_eq!(selected(&app), Some(12));
+
+        app.close_previews();
+        assert!(!app.previewing());
+    }
+}
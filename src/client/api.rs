@@ -1,8 +1,17 @@
-pub trait ApiUrlProvider {
+use crate::client::providers::ConfiguredProvider;
+use crate::client::response;
+use crate::model::{Board, Thread, ThreadPost};
+
+pub trait ApiUrlProvider: Send + Sync {
     fn boards(&self) -> String;
 
     fn threads(&self, board: &str, page: u8) -> String;
 
+    fn catalog(&self, board: &str) -> String;
+
+    /// `None` if the imageboard has no archive
+    fn archive(&self, board: &str) -> Option<String>;
+
     fn thread(&self, board: &str, no: u64) -> String;
 }
 
@@ -16,10 +25,37 @@ pub(crate) trait ContentUrlProvider {
     fn url_file(&self, board: &str, filename: String) -> String;
 }
 
-pub(crate) trait ChannelProvider: ContentUrlProvider + ApiUrlProvider {
+/// Turns API responses into models, default methods expect the 4chan JSON schema
+pub(crate) trait ResponseNormalizer: Send + Sync {
+    fn parse_boards(&self, body: &[u8]) -> serde_json::Result<Vec<Board>> {
+        response::parse_boards(body)
+    }
+
+    fn parse_threads(&self, body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+        response::parse_threads(body)
+    }
+
+    fn parse_catalog(&self, body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+        response::parse_catalog(body)
+    }
+
+    fn parse_archive(&self, body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+        response::parse_archive(body)
+    }
+
+    fn parse_thread(&self, body: &[u8]) -> serde_json::Result<Vec<ThreadPost>> {
+        response::parse_thread(body)
+    }
+}
+
+pub(crate) trait ChannelProvider:
+    ContentUrlProvider + ApiUrlProvider + ResponseNormalizer
+{
     fn as_api(&self) -> &dyn ApiUrlProvider;
 
     fn as_content(&self) -> &dyn ContentUrlProvider;
+
+    fn as_normalizer(&self) -> &dyn ResponseNormalizer;
 }
 
 pub(crate) struct Api4chan;
@@ -40,6 +76,14 @@ impl ApiUrlProvider for Api4chan {
         format!("{}/{}/{}.json", Self::BASE_API_URL, board, page)
     }
 
+    fn catalog(&self, board: &str) -> String {
+        format!("{}/{}/catalog.json", Self::BASE_API_URL, board)
+    }
+
+    fn archive(&self, board: &str) -> Option<String> {
+        Some(format!("{}/{}/archive.json", Self::BASE_API_URL, board))
+    }
+
     fn thread(&self, board: &str, no: u64) -> String {
         format!("{}/{}/thread/{}.json", Self::BASE_API_URL, board, no)
     }
@@ -63,6 +107,8 @@ impl ContentUrlProvider for Api4chan {
     }
 }
 
+impl ResponseNormalizer for Api4chan {}
+
 impl ChannelProvider for Api4chan {
     fn as_api(&self) -> &dyn ApiUrlProvider {
         self
@@ -71,11 +117,28 @@ impl ChannelProvider for Api4chan {
     fn as_content(&self) -> &dyn ContentUrlProvider {
         self
     }
+
+    fn as_normalizer(&self) -> &dyn ResponseNormalizer {
+        self
+    }
 }
 
 const DEFAULT_API: &str = "default";
 
-pub(crate) fn from_name(name: &str) -> Option<&'static dyn ChannelProvider> {
+/// Find provider by name, providers from the config file take precedence over built-in ones.
+///
+/// The provider is used for the whole run, so a configured one is leaked to be `'static`.
+pub(crate) fn from_name(
+    name: &str,
+    configured: Vec<ConfiguredProvider>,
+) -> Option<&'static dyn ChannelProvider> {
+    if let Some(provider) = configured
+        .into_iter()
+        .find(|provider| provider.name() == name)
+    {
+        return Some(Box::leak(Box::new(provider)));
+    }
+
     match name {
         DEFAULT_API | Api4chan::NAME => Some(&Api4chan {}),
         _ => None,
@@ -0,0 +1,99 @@
+use std::error::Error;
+use std::fmt;
+
+use reqwest::StatusCode;
+
+/// Error returned by `ChanClient` requests
+#[derive(Debug)]
+pub(crate) enum ClientError {
+    /// Connection failed or was interrupted
+    Network(reqwest::Error),
+    /// Server did not respond in time
+    Timeout,
+    /// Resource does not exist (anymore), e.g. a pruned or deleted thread
+    NotFound,
+    /// Resource was not modified since the last request
+    NotModified,
+    /// Too many requests were sent in a short time
+    TooManyRequests,
+    /// Any other unsuccessful HTTP status
+    Status(StatusCode),
+    /// Response body does not match the expected JSON schema
+    Decode(serde_json::Error),
+}
+
+impl ClientError {
+    pub(crate) fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => Self::NotFound,
+            StatusCode::NOT_MODIFIED => Self::NotModified,
+            StatusCode::TOO_MANY_REQUESTS => Self::TooManyRequests,
+            _ => Self::Status(status),
+        }
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(err) => write!(f, "network error ({})", err),
+            Self::Timeout => write!(f, "request timed out"),
+            Self::NotFound => write!(f, "not found (404)"),
+            Self::NotModified => write!(f, "not modified (304)"),
+            Self::TooManyRequests => write!(f, "too many requests (429)"),
+            Self::Status(status) => write!(f, "server responded with {}", status),
+            Self::Decode(err) => write!(f, "unexpected response ({})", err),
+        }
+    }
+}
+
+impl Error for ClientError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Network(err) => Some(err),
+            Self::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::Network(err)
+        }
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Decode(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert!(matches!(
+            ClientError::from_status(StatusCode::NOT_FOUND),
+            ClientError::NotFound
+        ));
+        assert!(matches!(
+            ClientError::from_status(StatusCode::NOT_MODIFIED),
+            ClientError::NotModified
+        ));
+        assert!(matches!(
+            ClientError::from_status(StatusCode::TOO_MANY_REQUESTS),
+            ClientError::TooManyRequests
+        ));
+        assert!(matches!(
+            ClientError::from_status(StatusCode::BAD_GATEWAY),
+            ClientError::Status(StatusCode::BAD_GATEWAY)
+        ));
+    }
+}
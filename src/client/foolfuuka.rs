@@ -0,0 +1,268 @@
+//! FoolFuuka archive JSON API, normalized into the 4chan schema of the models
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Map, Value};
+
+use crate::client::response::plain_text_to_html;
+use crate::model::ThreadPost;
+
+/// Thread response is an object keyed by the thread number
+type ThreadResponse = HashMap<String, ArchivedThread>;
+
+#[derive(Debug, Deserialize)]
+struct ArchivedThread {
+    op: ArchivedPost,
+    #[serde(default)]
+    posts: ArchivedReplies,
+}
+
+/// Replies are keyed by post number, an empty object is serialized as an array
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ArchivedReplies {
+    Map(HashMap<String, ArchivedPost>),
+    List(Vec<ArchivedPost>),
+}
+
+impl Default for ArchivedReplies {
+    fn default() -> Self {
+        Self::List(vec![])
+    }
+}
+
+/// Post object, numbers may be sent either as strings or as numbers
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ArchivedPost {
+    #[serde(deserialize_with = "number")]
+    num: u64,
+    /// Non-zero for ghost posts, made in the archive after the thread died
+    #[serde(deserialize_with = "number")]
+    subnum: u64,
+    #[serde(deserialize_with = "number")]
+    timestamp: u64,
+    name: Option<String>,
+    trip: Option<String>,
+    /// `N` for none, otherwise the first letter of the role
+    capcode: Option<String>,
+    poster_country: Option<String>,
+    title: Option<String>,
+    comment: Option<String>,
+    poster_hash: Option<String>,
+    #[serde(deserialize_with = "number")]
+    sticky: u64,
+    #[serde(deserialize_with = "number")]
+    locked: u64,
+    media: Option<ArchivedMedia>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ArchivedMedia {
+    /// Original filename as uploaded
+    media_filename: Option<String>,
+    /// Filename on the imageboard, the timestamp and extension
+    media_orig: Option<String>,
+    #[serde(deserialize_with = "number")]
+    media_size: u64,
+    #[serde(deserialize_with = "number")]
+    media_w: u64,
+    #[serde(deserialize_with = "number")]
+    media_h: u64,
+    #[serde(deserialize_with = "number")]
+    spoiler: u64,
+    /// Archived copy of the file, missing if the archive does not keep files
+    media_link: Option<String>,
+    remote_media_link: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(u64),
+    String(String),
+    Null,
+}
+
+fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(number) => Ok(number),
+        NumberOrString::String(number) => number.parse().map_err(serde::de::Error::custom),
+        NumberOrString::Null => Ok(0),
+    }
+}
+
+/// Posts of the archived thread, OP first.
+///
+/// Ghost posts are skipped, as they share the number of the post they follow.
+pub(super) fn parse_thread(body: &[u8]) -> serde_json::Result<Vec<ThreadPost>> {
+    let thread_response: ThreadResponse = serde_json::from_slice(body)?;
+
+    let Some(thread) = thread_response.into_values().next() else {
+        return Ok(vec![]);
+    };
+
+    let mut replies = match thread.posts {
+        ArchivedReplies::Map(posts) => posts.into_values().collect(),
+        ArchivedReplies::List(posts) => posts,
+    };
+    replies.retain(|post| post.subnum == 0);
+    replies.sort_by_key(|post| post.num);
+
+    let mut op = normalize_post(&thread.op);
+    op.insert("sticky".to_string(), json!(thread.op.sticky.min(1)));
+    op.insert("closed".to_string(), json!(thread.op.locked.min(1)));
+    op.insert("archived".to_string(), json!(1));
+    op.insert("replies".to_string(), json!(replies.len()));
+    op.insert(
+        "images".to_string(),
+        json!(replies.iter().filter(|post| post.media.is_some()).count()),
+    );
+
+    let mut posts = vec![serde_json::from_value(Value::Object(op))?];
+    for post in &replies {
+        posts.push(serde_json::from_value(Value::Object(normalize_post(post)))?);
+    }
+
+    Ok(posts)
+}
+
+/// Single post in the 4chan schema
+fn normalize_post(post: &ArchivedPost) -> Map<String, Value> {
+    let mut value = Map::new();
+    value.insert("no".to_string(), json!(post.num));
+    value.insert("time".to_string(), json!(post.timestamp));
+    value.insert(
+        "name".to_string(),
+        json!(htmlescape::encode_minimal(
+            post.name.as_deref().unwrap_or("")
+        )),
+    );
+    value.insert("trip".to_string(), json!(post.trip));
+    value.insert(
+        "capcode".to_string(),
+        json!(post.capcode.as_deref().and_then(capcode_name)),
+    );
+    value.insert("country".to_string(), json!(post.poster_country));
+    value.insert(
+        "sub".to_string(),
+        json!(htmlescape::encode_minimal(
+            post.title.as_deref().unwrap_or("")
+        )),
+    );
+    value.insert(
+        "com".to_string(),
+        json!(plain_text_to_html(post.comment.as_deref().unwrap_or(""))),
+    );
+    value.insert(
+        "id".to_string(),
+        json!(post.poster_hash.as_deref().unwrap_or("")),
+    );
+
+    if let Some(media) = &post.media {
+        let original = media.media_filename.as_deref().unwrap_or("");
+        let (filename, ext) = match original.rsplit_once('.') {
+            Some((filename, ext)) => (filename.to_string(), format!(".{}", ext)),
+            None => (original.to_string(), "".to_string()),
+        };
+        let tim = media
+            .media_orig
+            .as_deref()
+            .and_then(|orig| orig.split('.').next())
+            .and_then(|tim| tim.parse::<u64>().ok());
+
+        value.insert("filename".to_string(), json!(filename));
+        value.insert("ext".to_string(), json!(ext));
+        value.insert("tim".to_string(), json!(tim));
+        value.insert(
+            "fsize".to_string(),
+            json!((media.media_size > 0).then_some(media.media_size)),
+        );
+        value.insert("w".to_string(), json!(media.media_w));
+        value.insert("h".to_string(), json!(media.media_h));
+        value.insert("spoiler".to_string(), json!(media.spoiler.min(1)));
+        value.insert(
+            "file_url".to_string(),
+            json!(media
+                .media_link
+                .as_ref()
+                .or(media.remote_media_link.as_ref())),
+        );
+    }
+
+    value
+}
+
+/// 4chan capcode from the FoolFuuka role letter
+fn capcode_name(capcode: &str) -> Option<&'static str> {
+    match capcode {
+        "A" => Some("admin"),
+        "M" => Some("mod"),
+        "D" => Some("developer"),
+        "F" => Some("founder"),
+        "V" => Some("verified"),
+        "G" => Some("manager"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THREAD: &str = include_str!("fixtures/foolfuuka/thread.json");
+
+    #[test]
+    fn test_parse_thread() {
+        let posts = parse_thread(THREAD.as_bytes()).unwrap();
+        let numbers: Vec<usize> = posts.iter().map(|post| post.no()).collect();
+        assert_eq!(numbers, vec![5000, 5002, 5010]);
+
+        let op = &posts[0];
+        assert_eq!(op.time(), 1682942400);
+        assert_eq!(op.sub(), "Archived &lt;thread&gt;");
+        assert_eq!(op.closed(), 1);
+        assert_eq!(op.archived(), 1);
+        assert_eq!(op.replies(), 2);
+        assert_eq!(op.images(), 1);
+        assert_eq!(op.filename().as_deref(), Some("cat.picture"));
+        assert_eq!(op.ext().as_deref(), Some(".png"));
+        assert_eq!(op.tim(), Some(1682942400123));
+        assert_eq!(op.capcode(), None);
+        assert_eq!(op.fsize(), Some(123456));
+        assert_eq!((op.w(), op.h()), (Some(800), Some(600)));
+        assert_eq!(
+            op.file_url(),
+            Some("https://archive.example.org/g/image/1682/94/1682942400123.png")
+        );
+        assert_eq!(
+            op.com(),
+            "Still here<br><span class=\"quote\">&gt;implying</span><br>\
+            <a href=\"#p5002\" class=\"quotelink\">&gt;&gt;5002</a> see below"
+        );
+
+        assert_eq!(posts[1].com(), "");
+        assert_eq!(posts[1].file_url(), None);
+
+        let reply = &posts[2];
+        assert_eq!(reply.time(), 1682942700);
+        assert_eq!(reply.ext().as_deref(), Some(""));
+        assert_eq!(reply.fsize(), None);
+        assert_eq!(
+            reply.file_url(),
+            Some("https://archive.example.org/g/redirect/1682942700456")
+        );
+    }
+
+    #[test]
+    fn test_parse_thread_without_replies() {
+        const THREAD: &str = r#"{"7": {"op": {"num": 7, "comment": "alone"}, "posts": []}}"#;
+
+        let posts = parse_thread(THREAD.as_bytes()).unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].no(), 7);
+        assert_eq!(posts[0].replies(), 0);
+    }
+}
@@ -0,0 +1,285 @@
+//! Lynxchan JSON API, normalized into the 4chan schema of the models
+
+use chrono::DateTime;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::client::response::plain_text_to_html;
+use crate::model::{Board, Thread, ThreadPost};
+
+/// Lynxchan does not list the page count of boards, use a sensible default
+const DEFAULT_PAGES: isize = 10;
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BoardListResponse {
+    /// Since Lynxchan 2.0 the response is wrapped in a status object
+    Wrapped {
+        data: BoardList,
+    },
+    Plain(BoardList),
+}
+
+#[derive(Debug, Deserialize)]
+struct BoardList {
+    boards: Vec<LynxBoard>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LynxBoard {
+    board_uri: String,
+    board_name: String,
+    #[serde(default)]
+    board_description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThreadListResponse {
+    threads: Vec<LynxPost>,
+}
+
+/// Thread or post, threads have `threadId` and nested `posts`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct LynxPost {
+    #[serde(alias = "threadId")]
+    post_id: u64,
+    name: Option<String>,
+    subject: Option<String>,
+    message: Option<String>,
+    creation: Option<String>,
+    id: Option<String>,
+    pinned: bool,
+    locked: bool,
+    post_count: Option<u32>,
+    file_count: Option<u32>,
+    omitted_posts: u32,
+    omitted_files: u32,
+    files: Vec<LynxFile>,
+    posts: Vec<LynxPost>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LynxFile {
+    original_name: String,
+    path: String,
+}
+
+pub(super) fn parse_boards(body: &[u8]) -> serde_json::Result<Vec<Board>> {
+    let boards = match serde_json::from_slice(body)? {
+        BoardListResponse::Wrapped { data } | BoardListResponse::Plain(data) => data.boards,
+    };
+
+    boards
+        .into_iter()
+        .map(|board| {
+            serde_json::from_value(json!({
+                "board": board.board_uri,
+                "title": board.board_name,
+                "meta_description": htmlescape::encode_minimal(&board.board_description),
+                "per_page": 0,
+                "pages": DEFAULT_PAGES,
+                "bump_limit": 0,
+            }))
+        })
+        .collect()
+}
+
+pub(super) fn parse_threads(body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+    let threads_response: ThreadListResponse = serde_json::from_slice(body)?;
+
+    threads_response
+        .threads
+        .iter()
+        .map(normalize_thread)
+        .collect()
+}
+
+pub(super) fn parse_catalog(body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+    let catalog_response: Vec<LynxPost> = serde_json::from_slice(body)?;
+
+    catalog_response.iter().map(normalize_thread).collect()
+}
+
+pub(super) fn parse_thread(body: &[u8]) -> serde_json::Result<Vec<ThreadPost>> {
+    let thread_response: LynxPost = serde_json::from_slice(body)?;
+
+    normalize_posts(&thread_response)
+}
+
+fn normalize_thread(thread: &LynxPost) -> serde_json::Result<Thread> {
+    Ok(Thread::new(normalize_posts(thread)?))
+}
+
+/// OP post followed by the nested replies
+fn normalize_posts(thread: &LynxPost) -> serde_json::Result<Vec<ThreadPost>> {
+    let mut op = normalize_post(thread);
+    op.insert("sticky".to_string(), json!(thread.pinned as u8));
+    op.insert("closed".to_string(), json!(thread.locked as u8));
+    op.insert(
+        "replies".to_string(),
+        json!(thread
+            .post_count
+            .unwrap_or(thread.omitted_posts + thread.posts.len() as u32)),
+    );
+    op.insert(
+        "images".to_string(),
+        json!(thread.file_count.unwrap_or(
+            thread.omitted_files
+                + thread
+                    .posts
+                    .iter()
+                    .map(|p| p.files.len() as u32)
+                    .sum::<u32>()
+        )),
+    );
+
+    let mut posts = vec![serde_json::from_value(Value::Object(op))?];
+    for post in &thread.posts {
+        posts.push(serde_json::from_value(Value::Object(normalize_post(post)))?);
+    }
+
+    Ok(posts)
+}
+
+/// Single post in the 4chan schema, only the first file is kept
+fn normalize_post(post: &LynxPost) -> Map<String, Value> {
+    let time = post
+        .creation
+        .as_deref()
+        .and_then(|creation| DateTime::parse_from_rfc3339(creation).ok())
+        .map_or(0, |creation| creation.timestamp().max(0));
+
+    let mut value = Map::new();
+    value.insert("no".to_string(), json!(post.post_id));
+    value.insert("time".to_string(), json!(time));
+    value.insert(
+        "name".to_string(),
+        json!(htmlescape::encode_minimal(
+            post.name.as_deref().unwrap_or("")
+        )),
+    );
+    value.insert(
+        "sub".to_string(),
+        json!(htmlescape::encode_minimal(
+            post.subject.as_deref().unwrap_or("")
+        )),
+    );
+    value.insert(
+        "com".to_string(),
+        json!(plain_text_to_html(post.message.as_deref().unwrap_or(""))),
+    );
+    value.insert("id".to_string(), json!(post.id.as_deref().unwrap_or("")));
+
+    if let Some(file) = post.files.first() {
+        let (filename, ext) = match file.original_name.rsplit_once('.') {
+            Some((filename, ext)) => (filename.to_string(), format!(".{}", ext)),
+            None => (file.original_name.clone(), "".to_string()),
+        };
+
+        value.insert("filename".to_string(), json!(filename));
+        value.insert("ext".to_string(), json!(ext));
+        value.insert("file_path".to_string(), json!(file.path));
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARDS: &str = include_str!("fixtures/lynxchan/boards.json");
+    const PAGE: &str = include_str!("fixtures/lynxchan/page.json");
+    const CATALOG: &str = include_str!("fixtures/lynxchan/catalog.json");
+    const THREAD: &str = include_str!("fixtures/lynxchan/thread.json");
+
+    #[test]
+    fn test_parse_boards() {
+        let boards = parse_boards(BOARDS.as_bytes()).unwrap();
+
+        assert_eq!(boards.len(), 2);
+        assert_eq!(boards[0].board(), "b");
+        assert_eq!(boards[0].title(), "Random");
+        assert_eq!(boards[0].meta_description(), "Anything &amp; everything");
+        assert_eq!(boards[1].board(), "tech");
+
+        // older versions are not wrapped
+        let plain = r#"{"boards": [{"boardUri": "a", "boardName": "Anime"}]}"#;
+        assert_eq!(parse_boards(plain.as_bytes()).unwrap()[0].board(), "a");
+    }
+
+    #[test]
+    fn test_parse_threads() {
+        let threads = parse_threads(PAGE.as_bytes()).unwrap();
+        assert_eq!(threads.len(), 1);
+
+        let posts = threads[0].posts();
+        assert_eq!(posts.len(), 2);
+
+        let op = &posts[0];
+        assert_eq!(op.no(), 1000);
+        assert_eq!(op.sub(), "Lynx thread");
+        assert_eq!(op.time(), 1682942400);
+        assert_eq!(op.sticky(), 1);
+        assert_eq!(op.closed(), 0);
+        assert_eq!(op.replies(), 4);
+        assert_eq!(op.images(), 1);
+        assert_eq!(op.filename().as_deref(), Some("cat.picture"));
+        assert_eq!(op.ext().as_deref(), Some(".png"));
+        assert_eq!(
+            op.media_filename().as_deref(),
+            Some("/.media/0a1b2c3d4e5f.png")
+        );
+        assert_eq!(
+            op.com(),
+            "First line<br><span class=\"quote\">&gt;implying</span><br>\
+            <a href=\"#p1002\" class=\"quotelink\">&gt;&gt;1002</a> see below"
+        );
+
+        assert_eq!(posts[1].no(), 1005);
+        assert_eq!(posts[1].media_filename(), None);
+    }
+
+    #[test]
+    fn test_parse_catalog() {
+        let threads = parse_catalog(CATALOG.as_bytes()).unwrap();
+        assert_eq!(threads.len(), 2);
+
+        let op = &threads[0].posts()[0];
+        assert_eq!(op.no(), 1000);
+        assert_eq!(op.replies(), 4);
+        assert_eq!(op.images(), 2);
+
+        let op = &threads[1].posts()[0];
+        assert_eq!(op.no(), 990);
+        assert_eq!(op.sub(), "");
+        assert_eq!(op.closed(), 1);
+        assert_eq!(op.com(), "No subject &lt;b&gt;here&lt;/b&gt;");
+    }
+
+    #[test]
+    fn test_parse_thread() {
+        let posts = parse_thread(THREAD.as_bytes()).unwrap();
+        assert_eq!(posts.len(), 3);
+        assert_eq!(posts[0].no(), 1000);
+        assert_eq!(posts[0].replies(), 2);
+
+        let reply = &posts[1];
+        assert_eq!(reply.no(), 1002);
+        assert_eq!(reply.sub(), "");
+        assert_eq!(
+            reply.com(),
+            "<a href=\"#p1000\" class=\"quotelink\">&gt;&gt;1000</a><br>\
+            reply with &lt;tags&gt; &amp; entities"
+        );
+        assert_eq!(reply.filename().as_deref(), Some("noext"));
+        assert_eq!(reply.ext().as_deref(), Some(""));
+        assert_eq!(
+            reply.media_filename().as_deref(),
+            Some("/.media/ffeeddccbbaa")
+        );
+    }
+}
@@ -1,59 +1,240 @@
-use std::error::Error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
 
-use reqwest::Client;
+use reqwest::header::{IF_MODIFIED_SINCE, LAST_MODIFIED};
+use reqwest::{Client, StatusCode, Url};
+use tokio::time::{sleep_until, Instant};
 
-use crate::client::api::ApiUrlProvider;
-use crate::client::response::{BoardListResponse, ThreadListResponse, ThreadResponse};
+use crate::client::api::{ApiUrlProvider, ResponseNormalizer};
+pub(crate) use crate::client::error::ClientError;
+use crate::client::providers::ConfiguredArchive;
+use crate::lru::Lru;
 use crate::model::{Board, Thread, ThreadPost};
 
 pub(crate) mod api;
+mod error;
+mod foolfuuka;
+mod lynxchan;
+pub(crate) mod providers;
 mod response;
 
+/// Minimum delay between two requests to the same host
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Responses kept for `If-Modified-Since` requests, the least recently fetched are dropped
+const CACHED_RESPONSES: usize = 16;
+
 pub(crate) struct ChanClient {
     client: Client,
     api: &'static dyn ApiUrlProvider,
+    normalizer: &'static dyn ResponseNormalizer,
+    archives: Vec<ConfiguredArchive>,
+    scheduler: Scheduler,
+    cache: Mutex<Lru<String, CachedResponse>>,
 }
 
-type ClientResult<T> = Result<T, Box<dyn Error>>;
+pub(crate) type ClientResult<T> = Result<T, ClientError>;
 
 impl ChanClient {
-    pub(crate) fn new(client: Client, api: &'static dyn ApiUrlProvider) -> Self {
-        Self { api, client }
+    pub(crate) fn new(
+        client: Client,
+        api: &'static dyn ApiUrlProvider,
+        normalizer: &'static dyn ResponseNormalizer,
+        archives: Vec<ConfiguredArchive>,
+    ) -> Self {
+        Self {
+            api,
+            normalizer,
+            archives,
+            client,
+            scheduler: Scheduler::new(REQUEST_INTERVAL),
+            cache: Mutex::new(Lru::new(CACHED_RESPONSES)),
+        }
     }
 
     pub(crate) async fn get_boards(&self) -> ClientResult<Vec<Board>> {
-        let boards_response: BoardListResponse = self
-            .client
-            .get(self.api.boards())
-            .send()
-            .await?
-            .json::<BoardListResponse>()
-            .await?;
+        let body = self.get_body(self.api.boards()).await?;
 
-        Ok(boards_response.boards)
+        Ok(self.normalizer.parse_boards(&body)?)
     }
 
     pub(crate) async fn get_threads(&self, board: &str, page: u8) -> ClientResult<Vec<Thread>> {
-        let threads_response: ThreadListResponse = self
-            .client
-            .get(self.api.threads(board, page))
-            .send()
-            .await?
-            .json::<ThreadListResponse>()
-            .await?;
-
-        Ok(threads_response.threads)
-    }
-
-    pub(crate) async fn get_thread(&self, board: &str, no: u64) -> ClientResult<Vec<ThreadPost>> {
-        let thread_response: ThreadResponse = self
-            .client
-            .get(self.api.thread(board, no))
-            .send()
-            .await?
-            .json::<ThreadResponse>()
-            .await?;
-
-        Ok(thread_response.posts)
+        let body = self.get_body(self.api.threads(board, page)).await?;
+
+        Ok(self.normalizer.parse_threads(&body)?)
+    }
+
+    pub(crate) async fn get_catalog(&self, board: &str) -> ClientResult<Vec<Thread>> {
+        let body = self.get_body(self.api.catalog(board)).await?;
+
+        Ok(self.normalizer.parse_catalog(&body)?)
+    }
+
+    pub(crate) async fn get_archive(&self, board: &str) -> ClientResult<Vec<Thread>> {
+        let Some(url) = self.api.archive(board) else {
+            return Err(ClientError::NotFound);
+        };
+        let body = self.get_body(url).await?;
+
+        Ok(self.normalizer.parse_archive(&body)?)
+    }
+
+    /// Fetch thread posts, along with the name of the archive they came from.
+    ///
+    /// If the thread is gone and an archive covers the board, it is fetched from the archive.
+    /// When the archive fails too, the thread is still reported as not found.
+    pub(crate) async fn get_thread(
+        &self,
+        board: &str,
+        no: u64,
+    ) -> ClientResult<(Vec<ThreadPost>, Option<String>)> {
+        let err = match self.get_body(self.api.thread(board, no)).await {
+            Ok(body) => return Ok((self.normalizer.parse_thread(&body)?, None)),
+            Err(err @ ClientError::NotFound) => err,
+            Err(err) => return Err(err),
+        };
+
+        let Some(archive) = self.archives.iter().find(|archive| archive.covers(board)) else {
+            return Err(err);
+        };
+
+        // the thread is reported gone if the archive does not have it either
+        let Ok(body) = self.get_body(archive.thread(board, no)).await else {
+            return Err(err);
+        };
+        let Ok(posts) = foolfuuka::parse_thread(&body) else {
+            return Err(err);
+        };
+
+        Ok((posts, Some(archive.name().to_string())))
+    }
+
+    /// Fetch response body, respecting the request interval per host.
+    ///
+    /// Responses with `Last-Modified` are cached, and the next request to the same url
+    /// is sent with `If-Modified-Since`, so that `304 Not Modified` reuses the cached body.
+    async fn get_body(&self, url: String) -> ClientResult<Vec<u8>> {
+        let mut request = self.client.get(&url);
+        if let Some(cached) = self.cache.lock().unwrap().get(&url) {
+            request = request.header(IF_MODIFIED_SINCE, &cached.last_modified);
+        }
+
+        let host = Url::parse(&url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        // the slot is taken only once the request is sent, so aborted requests do not hold one
+        while let Err(slot) = self.scheduler.try_reserve(&host, Instant::now()) {
+            sleep_until(slot).await;
+        }
+
+        let response = request.send().await?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = self.cache.lock().unwrap().get(&url) {
+                return Ok(cached.body.clone());
+            }
+        }
+
+        if !status.is_success() {
+            return Err(ClientError::from_status(status));
+        }
+
+        let last_modified = response
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let body = response.bytes().await?.to_vec();
+
+        if let Some(last_modified) = last_modified {
+            self.cache.lock().unwrap().insert(
+                url,
+                CachedResponse {
+                    last_modified,
+                    body: body.clone(),
+                },
+            );
+        }
+
+        Ok(body)
+    }
+}
+
+struct CachedResponse {
+    last_modified: String,
+    body: Vec<u8>,
+}
+
+/// Hands out request slots, at most one per interval for each host
+struct Scheduler {
+    interval: Duration,
+    next_slots: Mutex<HashMap<String, Instant>>,
+}
+
+impl Scheduler {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take the slot of the host if it is free at `now`, otherwise returns when it frees up
+    fn try_reserve(&self, host: &str, now: Instant) -> Result<(), Instant> {
+        let mut next_slots = self.next_slots.lock().unwrap();
+        if let Some(next) = next_slots.get(host).filter(|next| **next > now) {
+            return Err(*next);
+        }
+        next_slots.insert(host.to_string(), now + self.interval);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduler_reserve() {
+        let scheduler = Scheduler::new(REQUEST_INTERVAL);
+        let now = Instant::now();
+
+        assert_eq!(scheduler.try_reserve("a.4cdn.org", now), Ok(()));
+        assert_eq!(
+            scheduler.try_reserve("a.4cdn.org", now),
+            Err(now + REQUEST_INTERVAL)
+        );
+
+        // other hosts are independent
+        assert_eq!(scheduler.try_reserve("i.4cdn.org", now), Ok(()));
+
+        // slots in the past are not reused
+        let later = now + REQUEST_INTERVAL * 10;
+        assert_eq!(scheduler.try_reserve("a.4cdn.org", later), Ok(()));
+        assert_eq!(
+            scheduler.try_reserve("a.4cdn.org", later),
+            Err(later + REQUEST_INTERVAL)
+        );
+    }
+
+    #[test]
+    fn test_scheduler_aborted_request() {
+        let scheduler = Scheduler::new(REQUEST_INTERVAL);
+        let now = Instant::now();
+        assert_eq!(scheduler.try_reserve("a.4cdn.org", now), Ok(()));
+
+        // requests that wait and are dropped before being sent do not push back the next one
+        for _ in 0..9 {
+            assert_eq!(
+                scheduler.try_reserve("a.4cdn.org", now),
+                Err(now + REQUEST_INTERVAL)
+            );
+        }
+        let slot = now + REQUEST_INTERVAL;
+        assert_eq!(scheduler.try_reserve("a.4cdn.org", slot), Ok(()));
     }
 }
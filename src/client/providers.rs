@@ -0,0 +1,581 @@
+use crate::client::api::{ApiUrlProvider, ChannelProvider, ContentUrlProvider, ResponseNormalizer};
+use crate::client::{lynxchan, response};
+use crate::config::{parse_sections, ConfigError, Section};
+use crate::model::{Board, Thread, ThreadPost};
+
+/// Imageboard defined in `providers.conf`
+#[derive(Debug, PartialEq)]
+pub(crate) struct ConfiguredProvider {
+    name: String,
+    engine: Engine,
+    api_url: String,
+    content_url: String,
+    media_url: String,
+    templates: UrlTemplates,
+}
+
+/// FoolFuuka archive defined in `providers.conf`, used for dead threads of the boards it covers
+#[derive(Debug, PartialEq)]
+pub(crate) struct ConfiguredArchive {
+    name: String,
+    api_url: String,
+    boards: Vec<String>,
+}
+
+/// Imageboards and archives defined in `providers.conf`
+#[derive(Debug, PartialEq)]
+pub(crate) struct ProvidersFile {
+    pub(crate) providers: Vec<ConfiguredProvider>,
+    pub(crate) archives: Vec<ConfiguredArchive>,
+}
+
+/// Url templates, placeholders in braces are replaced with actual values
+#[derive(Debug, PartialEq)]
+struct UrlTemplates {
+    boards: String,
+    threads: String,
+    catalog: String,
+    /// `None` if the imageboard has no archive
+    archive: Option<String>,
+    thread: String,
+    url_board: String,
+    url_thread: String,
+    url_thread_post: String,
+    url_file: String,
+}
+
+/// Imageboard software, which defines the url layout and the JSON schema
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Engine {
+    /// 4chan JSON API shape, also used by vichan
+    Vichan,
+    Lynxchan,
+}
+
+impl Engine {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vichan" | "4chan" => Some(Self::Vichan),
+            "lynxchan" => Some(Self::Lynxchan),
+            _ => None,
+        }
+    }
+}
+
+impl UrlTemplates {
+    fn for_engine(engine: Engine) -> Self {
+        match engine {
+            Engine::Vichan => Self::vichan(),
+            Engine::Lynxchan => Self::lynxchan(),
+        }
+    }
+
+    /// Same layout as 4chan
+    fn vichan() -> Self {
+        Self {
+            boards: "{api_url}/boards.json".to_string(),
+            threads: "{api_url}/{board}/{page}.json".to_string(),
+            catalog: "{api_url}/{board}/catalog.json".to_string(),
+            archive: Some("{api_url}/{board}/archive.json".to_string()),
+            thread: "{api_url}/{board}/thread/{no}.json".to_string(),
+            url_board: "{content_url}/{board}/".to_string(),
+            url_thread: "{content_url}/{board}/thread/{no}".to_string(),
+            url_thread_post: "{content_url}/{board}/thread/{no}#p{post_no}".to_string(),
+            url_file: "{media_url}/{board}/{filename}".to_string(),
+        }
+    }
+
+    /// Lynxchan has no archive, files are referenced by their full path
+    fn lynxchan() -> Self {
+        Self {
+            boards: "{api_url}/boards.js?json=1".to_string(),
+            threads: "{api_url}/{board}/{page}.json".to_string(),
+            catalog: "{api_url}/{board}/catalog.json".to_string(),
+            archive: None,
+            thread: "{api_url}/{board}/res/{no}.json".to_string(),
+            url_board: "{content_url}/{board}/".to_string(),
+            url_thread: "{content_url}/{board}/res/{no}.html".to_string(),
+            url_thread_post: "{content_url}/{board}/res/{no}.html#{post_no}".to_string(),
+            url_file: "{media_url}{filename}".to_string(),
+        }
+    }
+}
+
+impl ConfiguredProvider {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parse a single `[name]` section of the providers file
+    fn from_section(section: &Section) -> Result<Self, ConfigError> {
+        // Engine decides the default templates, so it is read first
+        let engine = match section.entries.iter().find(|entry| entry.key == "engine") {
+            Some(entry) => {
+                Engine::from_name(entry.value).ok_or_else(|| ConfigError::InvalidValue {
+                    line_no: entry.line_no,
+                    key: entry.key.to_string(),
+                })?
+            }
+            None => Engine::Vichan,
+        };
+
+        let mut api_url = None;
+        let mut content_url = None;
+        let mut media_url = None;
+        let mut templates = UrlTemplates::for_engine(engine);
+
+        for entry in &section.entries {
+            let value = entry.value.trim_end_matches('/').to_string();
+            let field = match entry.key {
+                "api_url" => &mut api_url,
+                "content_url" => &mut content_url,
+                "media_url" => &mut media_url,
+                "engine" => continue,
+                "archive" => {
+                    templates.archive = Some(entry.value.to_string());
+                    continue;
+                }
+                key => {
+                    let template = match key {
+                        "boards" => &mut templates.boards,
+                        "threads" => &mut templates.threads,
+                        "catalog" => &mut templates.catalog,
+                        "thread" => &mut templates.thread,
+                        "url_board" => &mut templates.url_board,
+                        "url_thread" => &mut templates.url_thread,
+                        "url_thread_post" => &mut templates.url_thread_post,
+                        "url_file" => &mut templates.url_file,
+                        _ => {
+                            return Err(ConfigError::UnknownKey {
+                                line_no: entry.line_no,
+                                key: key.to_string(),
+                            })
+                        }
+                    };
+                    *template = entry.value.to_string();
+                    continue;
+                }
+            };
+            *field = Some(value);
+        }
+
+        let required = |field: Option<String>, key: &str| {
+            field.ok_or_else(|| ConfigError::MissingKey {
+                line_no: section.line_no,
+                key: key.to_string(),
+            })
+        };
+
+        Ok(Self {
+            name: section.name.to_string(),
+            engine,
+            api_url: required(api_url, "api_url")?,
+            content_url: required(content_url, "content_url")?,
+            media_url: required(media_url, "media_url")?,
+            templates,
+        })
+    }
+
+    /// Fill in the template, base urls are always available
+    fn expand(&self, template: &str, values: &[(&str, &str)]) -> String {
+        let base = [
+            ("api_url", self.api_url.as_str()),
+            ("content_url", self.content_url.as_str()),
+            ("media_url", self.media_url.as_str()),
+        ];
+
+        base.iter()
+            .chain(values)
+            .fold(template.to_string(), |url, (key, value)| {
+                url.replace(&format!("{{{}}}", key), value)
+            })
+    }
+}
+
+impl ConfiguredArchive {
+    /// Engine name which marks a section as an archive
+    const ENGINE: &'static str = "foolfuuka";
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn covers(&self, board: &str) -> bool {
+        self.boards.iter().any(|covered| covered == board)
+    }
+
+    pub(crate) fn thread(&self, board: &str, no: u64) -> String {
+        format!(
+            "{}/_/api/chan/thread/?board={}&num={}",
+            self.api_url, board, no
+        )
+    }
+
+    /// Parse a single `[name]` section with `engine=foolfuuka`
+    fn from_section(section: &Section) -> Result<Self, ConfigError> {
+        let mut api_url = None;
+        let mut boards = None;
+
+        for entry in &section.entries {
+            match entry.key {
+                "api_url" => api_url = Some(entry.value.trim_end_matches('/').to_string()),
+                "boards" => {
+                    boards = Some(
+                        entry
+                            .value
+                            .split(',')
+                            .map(|board| board.trim().trim_matches('/').to_string())
+                            .filter(|board| !board.is_empty())
+                            .collect(),
+                    )
+                }
+                "engine" => {}
+                key => {
+                    return Err(ConfigError::UnknownKey {
+                        line_no: entry.line_no,
+                        key: key.to_string(),
+                    })
+                }
+            }
+        }
+
+        let missing = |key: &str| ConfigError::MissingKey {
+            line_no: section.line_no,
+            key: key.to_string(),
+        };
+
+        Ok(Self {
+            name: section.name.to_string(),
+            api_url: api_url.ok_or_else(|| missing("api_url"))?,
+            boards: boards.ok_or_else(|| missing("boards"))?,
+        })
+    }
+}
+
+impl ApiUrlProvider for ConfiguredProvider {
+    fn boards(&self) -> String {
+        self.expand(&self.templates.boards, &[])
+    }
+
+    fn threads(&self, board: &str, page: u8) -> String {
+        self.expand(
+            &self.templates.threads,
+            &[("board", board), ("page", &page.to_string())],
+        )
+    }
+
+    fn catalog(&self, board: &str) -> String {
+        self.expand(&self.templates.catalog, &[("board", board)])
+    }
+
+    fn archive(&self, board: &str) -> Option<String> {
+        let template = self.templates.archive.as_ref()?;
+        Some(self.expand(template, &[("board", board)]))
+    }
+
+    fn thread(&self, board: &str, no: u64) -> String {
+        self.expand(
+            &self.templates.thread,
+            &[("board", board), ("no", &no.to_string())],
+        )
+    }
+}
+
+impl ContentUrlProvider for ConfiguredProvider {
+    fn url_board(&self, board: &str) -> String {
+        self.expand(&self.templates.url_board, &[("board", board)])
+    }
+
+    fn url_thread(&self, board: &str, no: u64) -> String {
+        self.expand(
+            &self.templates.url_thread,
+            &[("board", board), ("no", &no.to_string())],
+        )
+    }
+
+    fn url_thread_post(&self, board: &str, no: u64, post_no: u64) -> String {
+        self.expand(
+            &self.templates.url_thread_post,
+            &[
+                ("board", board),
+                ("no", &no.to_string()),
+                ("post_no", &post_no.to_string()),
+            ],
+        )
+    }
+
+    fn url_file(&self, board: &str, filename: String) -> String {
+        self.expand(
+            &self.templates.url_file,
+            &[("board", board), ("filename", &filename)],
+        )
+    }
+}
+
+impl ResponseNormalizer for ConfiguredProvider {
+    fn parse_boards(&self, body: &[u8]) -> serde_json::Result<Vec<Board>> {
+        match self.engine {
+            Engine::Vichan => response::parse_boards(body),
+            Engine::Lynxchan => lynxchan::parse_boards(body),
+        }
+    }
+
+    fn parse_threads(&self, body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+        match self.engine {
+            Engine::Vichan => response::parse_threads(body),
+            Engine::Lynxchan => lynxchan::parse_threads(body),
+        }
+    }
+
+    fn parse_catalog(&self, body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+        match self.engine {
+            Engine::Vichan => response::parse_catalog(body),
+            Engine::Lynxchan => lynxchan::parse_catalog(body),
+        }
+    }
+
+    fn parse_thread(&self, body: &[u8]) -> serde_json::Result<Vec<ThreadPost>> {
+        match self.engine {
+            Engine::Vichan => response::parse_thread(body),
+            Engine::Lynxchan => lynxchan::parse_thread(body),
+        }
+    }
+}
+
+impl ChannelProvider for ConfiguredProvider {
+    fn as_api(&self) -> &dyn ApiUrlProvider {
+        self
+    }
+
+    fn as_content(&self) -> &dyn ContentUrlProvider {
+        self
+    }
+
+    fn as_normalizer(&self) -> &dyn ResponseNormalizer {
+        self
+    }
+}
+
+/// Parse providers configuration file (`providers.conf`)
+pub(crate) fn parse_providers_file(file: &str) -> Result<ProvidersFile, ConfigError> {
+    let sections = parse_sections(file)?;
+
+    let mut providers = vec![];
+    let mut archives = vec![];
+    for section in &sections {
+        // Every provider must be in its own section
+        if section.name.is_empty() {
+            if let Some(entry) = section.entries.first() {
+                return Err(ConfigError::UnknownKey {
+                    line_no: entry.line_no,
+                    key: entry.key.to_string(),
+                });
+            }
+            continue;
+        }
+
+        let is_archive = section
+            .entries
+            .iter()
+            .any(|entry| entry.key == "engine" && entry.value == ConfiguredArchive::ENGINE);
+        if is_archive {
+            archives.push(ConfiguredArchive::from_section(section)?);
+        } else {
+            providers.push(ConfiguredProvider::from_section(section)?);
+        }
+    }
+
+    Ok(ProvidersFile {
+        providers,
+        archives,
+    })
+}
+
+/// Get contents of providers file, with commented out examples
+pub(crate) fn default_providers_file_contents() -> String {
+    String::from(
+        r#"# Imageboard providers for tui-chan
+# https://github.com/tuqqu/tui-chan
+#
+# Each section defines an imageboard, run `tui-chan <name>` to use it.
+#
+# Required keys are the base urls: api_url, content_url and media_url.
+# Optional engine is either `vichan` (4chan-compatible JSON API, default) or `lynxchan`.
+# Url templates are optional and default to the layout of the engine, for vichan:
+#   boards={api_url}/boards.json
+#   threads={api_url}/{board}/{page}.json
+#   catalog={api_url}/{board}/catalog.json
+#   archive={api_url}/{board}/archive.json
+#   thread={api_url}/{board}/thread/{no}.json
+#   url_board={content_url}/{board}/
+#   url_thread={content_url}/{board}/thread/{no}
+#   url_thread_post={content_url}/{board}/thread/{no}#p{post_no}
+#   url_file={media_url}/{board}/{filename}
+# Lynxchan has no archive of threads, unless an archive template is given.
+#
+# Sections with `engine=foolfuuka` define archives instead. Threads that are gone from
+# the imageboard are fetched from the first archive listing their board.
+# Required keys are api_url and boards, a comma-separated list.
+
+#[vichan-example]
+#api_url=https://vichan.example.org
+#content_url=https://vichan.example.org
+#media_url=https://vichan.example.org
+#thread={api_url}/{board}/res/{no}.json
+#url_thread={content_url}/{board}/res/{no}.html
+#url_thread_post={content_url}/{board}/res/{no}.html#{post_no}
+#url_file={media_url}/{board}/src/{filename}
+
+#[lynxchan-example]
+#engine=lynxchan
+#api_url=https://lynxchan.example.org
+#content_url=https://lynxchan.example.org
+#media_url=https://lynxchan.example.org
+
+#[foolfuuka-example]
+#engine=foolfuuka
+#api_url=https://archive.example.org
+#boards=a,g,tv
+
+#[local-mock]
+#api_url=http://localhost:8080
+#content_url=http://localhost:8080
+#media_url=http://localhost:8080
+"#,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_providers_file_works() {
+        const FILE: &str = "[mirror]\n\
+            api_url=https://api.example.org/\n\
+            content_url=https://example.org\n\
+            media_url=https://media.example.org\n\
+            thread={api_url}/{board}/res/{no}.json\n";
+
+        let providers = parse_providers_file(FILE).unwrap().providers;
+        assert_eq!(providers.len(), 1);
+
+        let provider = &providers[0];
+        assert_eq!(provider.name(), "mirror");
+        assert_eq!(provider.boards(), "https://api.example.org/boards.json");
+        assert_eq!(provider.threads("g", 2), "https://api.example.org/g/2.json");
+        assert_eq!(
+            provider.thread("g", 123),
+            "https://api.example.org/g/res/123.json"
+        );
+        assert_eq!(
+            provider.url_thread_post("g", 123, 456),
+            "https://example.org/g/thread/123#p456"
+        );
+        assert_eq!(
+            provider.url_file("g", "1.png".to_string()),
+            "https://media.example.org/g/1.png"
+        );
+
+        assert_eq!(
+            parse_providers_file("[mirror]\napi_url=a\ncontent_url=b"),
+            Err(ConfigError::MissingKey {
+                line_no: 1,
+                key: "media_url".to_string()
+            })
+        );
+        assert_eq!(
+            parse_providers_file("[mirror]\nunknown=a"),
+            Err(ConfigError::UnknownKey {
+                line_no: 2,
+                key: "unknown".to_string()
+            })
+        );
+        assert_eq!(
+            parse_providers_file("api_url=a"),
+            Err(ConfigError::UnknownKey {
+                line_no: 1,
+                key: "api_url".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_lynxchan_provider_works() {
+        const FILE: &str = "[lynx]\n\
+            engine=lynxchan\n\
+            api_url=https://lynx.example.org\n\
+            content_url=https://lynx.example.org\n\
+            media_url=https://lynx.example.org\n";
+
+        let provider = &parse_providers_file(FILE).unwrap().providers[0];
+        assert_eq!(provider.engine, Engine::Lynxchan);
+        assert_eq!(
+            provider.boards(),
+            "https://lynx.example.org/boards.js?json=1"
+        );
+        assert_eq!(
+            provider.thread("b", 1),
+            "https://lynx.example.org/b/res/1.json"
+        );
+        assert_eq!(
+            provider.url_file("b", "/.media/abc.png".to_string()),
+            "https://lynx.example.org/.media/abc.png"
+        );
+        assert_eq!(provider.archive("b"), None);
+
+        assert_eq!(
+            parse_providers_file("[lynx]\nengine=unknown"),
+            Err(ConfigError::InvalidValue {
+                line_no: 2,
+                key: "engine".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn default_providers_file_is_valid() {
+        assert_eq!(
+            parse_providers_file(&default_providers_file_contents()),
+            Ok(ProvidersFile {
+                providers: vec![],
+                archives: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_foolfuuka_archive_works() {
+        const FILE: &str = "[archive]\n\
+            engine=foolfuuka\n\
+            api_url=https://archive.example.org/\n\
+            boards=a, /g/,tv\n";
+
+        let file = parse_providers_file(FILE).unwrap();
+        assert!(file.providers.is_empty());
+
+        let archive = &file.archives[0];
+        assert_eq!(archive.name(), "archive");
+        assert!(archive.covers("g"));
+        assert!(!archive.covers("b"));
+        assert_eq!(
+            archive.thread("g", 123),
+            "https://archive.example.org/_/api/chan/thread/?board=g&num=123"
+        );
+
+        assert_eq!(
+            parse_providers_file("[archive]\nengine=foolfuuka\napi_url=a"),
+            Err(ConfigError::MissingKey {
+                line_no: 1,
+                key: "boards".to_string()
+            })
+        );
+        assert_eq!(
+            parse_providers_file("[archive]\nengine=foolfuuka\nmedia_url=a"),
+            Err(ConfigError::UnknownKey {
+                line_no: 3,
+                key: "media_url".to_string()
+            })
+        );
+    }
+}
@@ -1,18 +1,223 @@
+//! Responses of the 4chan JSON API, which is also the schema of the models
+
+use std::sync::LazyLock;
+
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use crate::model::{Board, Thread, ThreadPost};
 
 #[derive(Debug, Serialize, Deserialize)]
-pub(super) struct BoardListResponse {
-    pub(super) boards: Vec<Board>,
+struct BoardListResponse {
+    boards: Vec<Board>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ThreadListResponse {
+    threads: Vec<Thread>,
 }
 
+/// `catalog.json` is an array of pages
 #[derive(Debug, Serialize, Deserialize)]
-pub(super) struct ThreadListResponse {
-    pub(super) threads: Vec<Thread>,
+struct CatalogPageResponse {
+    threads: Vec<CatalogThreadResponse>,
 }
 
+/// Catalog entry is the OP post, with the latest replies nested in it
 #[derive(Debug, Serialize, Deserialize)]
-pub(super) struct ThreadResponse {
-    pub(super) posts: Vec<ThreadPost>,
+struct CatalogThreadResponse {
+    #[serde(flatten)]
+    op: ThreadPost,
+    #[serde(default)]
+    last_replies: Vec<ThreadPost>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ThreadResponse {
+    posts: Vec<ThreadPost>,
+}
+
+pub(super) fn parse_boards(body: &[u8]) -> serde_json::Result<Vec<Board>> {
+    let boards_response: BoardListResponse = serde_json::from_slice(body)?;
+
+    Ok(boards_response.boards)
+}
+
+pub(super) fn parse_threads(body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+    let threads_response: ThreadListResponse = serde_json::from_slice(body)?;
+
+    Ok(threads_response.threads)
+}
+
+pub(super) fn parse_catalog(body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+    let pages_response: Vec<CatalogPageResponse> = serde_json::from_slice(body)?;
+
+    let threads = pages_response
+        .into_iter()
+        .flat_map(|page| page.threads)
+        .map(|thread| {
+            let mut posts = vec![thread.op];
+            posts.extend(thread.last_replies);
+            Thread::new(posts)
+        })
+        .collect();
+
+    Ok(threads)
+}
+
+/// Archived threads, newest first.
+///
+/// The archive only lists thread numbers, so threads contain a single stub post.
+pub(super) fn parse_archive(body: &[u8]) -> serde_json::Result<Vec<Thread>> {
+    let archive_response: Vec<u64> = serde_json::from_slice(body)?;
+
+    let threads = archive_response
+        .into_iter()
+        .rev()
+        .map(|no| Thread::new(vec![ThreadPost::archived_stub(no as usize)]))
+        .collect();
+
+    Ok(threads)
+}
+
+pub(super) fn parse_thread(body: &[u8]) -> serde_json::Result<Vec<ThreadPost>> {
+    let thread_response: ThreadResponse = serde_json::from_slice(body)?;
+
+    Ok(thread_response.posts)
+}
+
+/// Convert plain text message into 4chan comment markup, for imageboards without HTML comments
+pub(super) fn plain_text_to_html(message: &str) -> String {
+    static QUOTE_LINK: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"&gt;&gt;(\d+)").expect("Quote link regex must be valid"));
+
+    message
+        .lines()
+        .map(|line| {
+            let escaped = htmlescape::encode_minimal(line);
+            let linked = QUOTE_LINK.replace_all(
+                &escaped,
+                r##"<a href="#p$1" class="quotelink">&gt;&gt;$1</a>"##,
+            );
+
+            if line.starts_with('>') && !line.starts_with(">>") {
+                format!(r#"<span class="quote">{}</span>"#, linked)
+            } else {
+                linked.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_boards() {
+        const BOARDS: &str = r#"{"boards": [
+            {"board": "3", "title": "3DCG", "ws_board": 1, "per_page": 15, "pages": 10,
+             "max_filesize": 4194304, "max_webm_filesize": 3145728, "max_comment_chars": 2000,
+             "max_webm_duration": 120, "bump_limit": 310, "image_limit": 150,
+             "cooldowns": {"threads": 600, "replies": 60, "images": 60},
+             "meta_description": "&quot;/3/ - 3DCG&quot; is 4chan's board for 3D modeling.",
+             "is_archived": 1, "code_tags": 1},
+            {"board": "pol", "title": "Politically Incorrect", "ws_board": 0, "per_page": 15,
+             "pages": 10, "bump_limit": 300, "meta_description": "",
+             "board_flags": {"TR": "Tree Hugger", "AC": "Anarcho-Capitalist"}}
+        ]}"#;
+
+        let boards = parse_boards(BOARDS.as_bytes()).unwrap();
+        assert_eq!(boards.len(), 2);
+
+        let board = &boards[0];
+        assert!(!board.nsfw());
+        assert_eq!(board.max_filesize(), Some(4194304));
+        assert_eq!(board.max_comment_chars(), Some(2000));
+        assert_eq!(board.image_limit(), Some(150));
+        assert_eq!(board.cooldowns().map(|c| c.threads()), Some(600));
+        assert_eq!(board.is_archived(), 1);
+        assert_eq!(board.code_tags(), 1);
+        assert_eq!(board.user_ids(), 0);
+        assert!(board.board_flags().is_none());
+
+        let board = &boards[1];
+        assert!(board.nsfw());
+        assert_eq!(board.board_flags().map(|flags| flags.len()), Some(2));
+    }
+
+    #[test]
+    fn test_parse_catalog() {
+        const CATALOG: &str = r#"[
+            {"page": 1, "threads": [
+                {"no": 100, "sub": "first", "replies": 2, "images": 1, "last_replies": [
+                    {"no": 101, "com": "reply"}, {"no": 102, "com": "another reply"}
+                ]},
+                {"no": 200, "replies": 0}
+            ]},
+            {"page": 2, "threads": [{"no": 300}]}
+        ]"#;
+
+        let threads = parse_catalog(CATALOG.as_bytes()).unwrap();
+        assert_eq!(threads.len(), 3);
+
+        let posts = threads[0].posts();
+        assert_eq!(posts.len(), 3);
+        assert_eq!(posts[0].no(), 100);
+        assert_eq!(posts[0].sub(), "first");
+        assert_eq!(posts[0].images(), 1);
+        assert_eq!(posts[2].com(), "another reply");
+        assert_eq!(threads[1].posts().len(), 1);
+        assert_eq!(threads[2].posts()[0].no(), 300);
+    }
+
+    #[test]
+    fn test_parse_thread() {
+        const THREAD: &str = r#"{"posts": [
+            {"no": 100, "resto": 0, "time": 1617810439, "name": "Anonymous", "trip": "!Ep8pui8Vw2",
+             "capcode": "mod", "country": "US", "country_name": "United States",
+             "filename": "cat", "ext": ".png", "tim": 1617810439123, "fsize": 123456,
+             "w": 800, "h": 600, "tn_w": 250, "tn_h": 187, "md5": "abcdef==", "spoiler": 1,
+             "custom_spoiler": 3, "bumplimit": 1, "imagelimit": 0, "unique_ips": 42,
+             "semantic_url": "a-thread", "tag": "Other", "archived": 1, "archived_on": 1617900000},
+            {"no": 101, "resto": 100, "time": 1617810500, "board_flag": "TR",
+             "flag_name": "Tree Hugger", "filedeleted": 1}
+        ]}"#;
+
+        let posts = parse_thread(THREAD.as_bytes()).unwrap();
+        assert_eq!(posts.len(), 2);
+
+        let op = &posts[0];
+        assert_eq!(op.resto(), 0);
+        assert_eq!(op.trip(), Some("!Ep8pui8Vw2"));
+        assert_eq!(op.capcode(), Some("mod"));
+        assert_eq!(op.country(), Some("US"));
+        assert_eq!(op.fsize(), Some(123456));
+        assert_eq!((op.w(), op.h()), (Some(800), Some(600)));
+        assert_eq!(op.spoiler(), 1);
+        assert_eq!(op.custom_spoiler(), Some(3));
+        assert_eq!(op.bumplimit(), 1);
+        assert_eq!(op.imagelimit(), 0);
+        assert_eq!(op.unique_ips(), Some(42));
+        assert_eq!(op.archived_on(), Some(1617900000));
+        assert_eq!(op.tag(), Some("Other"));
+
+        let reply = &posts[1];
+        assert_eq!(reply.resto(), 100);
+        assert_eq!(reply.board_flag(), Some("TR"));
+        assert_eq!(reply.flag_name(), Some("Tree Hugger"));
+        assert_eq!(reply.filedeleted(), 1);
+        assert_eq!(reply.fsize(), None);
+    }
+
+    #[test]
+    fn test_parse_archive() {
+        let threads = parse_archive(b"[1, 2, 3]").unwrap();
+        let numbers: Vec<usize> = threads.iter().map(|t| t.posts()[0].no()).collect();
+
+        assert_eq!(numbers, vec![3, 2, 1]);
+        assert!(threads[0].posts()[0].stub());
+        assert_eq!(threads[0].posts()[0].archived(), 1);
+    }
 }
@@ -0,0 +1,266 @@
+use std::{env, fs, io, path::Path};
+
+/// Read file in config directory as string, and create new file if it does not exist
+pub fn read_or_create_config_file(
+    filename: &str,
+    default_contents: fn() -> String,
+) -> Result<String, io::Error> {
+    // Find config folder or use default
+    let Ok(config) = get_config_folder() else {
+        eprintln!("Could not find home config folder file. Continuing with default config.");
+        return Ok(default_contents());
+    };
+
+    let folder = format!("{config}/tui-chan");
+    let filepath = format!("{folder}/{filename}");
+
+    // Create folder if it does not exist (non-recursive)
+    if !Path::new(&folder).exists() {
+        fs::create_dir(&folder)?;
+    }
+
+    // Create file if it does not exist
+    if !Path::new(&filepath).exists() {
+        let default_contents = default_contents();
+        fs::write(&filepath, &default_contents)?;
+        // Return contents
+        return Ok(default_contents);
+    }
+
+    // Read file
+    fs::read_to_string(&filepath)
+}
+
+/// Get config home folder for Linux
+fn get_config_folder() -> Result<String, env::VarError> {
+    env::var("XDG_CONFIG_HOME")
+        .or_else(|_| env::var("HOME").map(|home| format!("{}/.config", home)))
+}
+
+/// Named group of `key=value` entries, started by a `[name]` line
+#[derive(Debug, PartialEq)]
+pub struct Section<'a> {
+    /// Line number of the section header, 0 for entries before any header
+    pub line_no: usize,
+    /// Section name, empty for entries before any header
+    pub name: &'a str,
+    pub entries: Vec<Entry<'a>>,
+}
+
+/// Single `key=value` line
+#[derive(Debug, PartialEq)]
+pub struct Entry<'a> {
+    pub line_no: usize,
+    pub key: &'a str,
+    pub value: &'a str,
+}
+
+/// Error parsing configuration file
+#[derive(Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum ConfigError {
+    /// Section header is not closed with `]`, has no name, or is not allowed in this file
+    InvalidSection {
+        /// Line number
+        line_no: usize,
+    },
+
+    /// Section with the same name was already defined in file
+    SectionAlreadyDefined {
+        /// Line number
+        line_no: usize,
+        /// Name of section
+        name: String,
+    },
+
+    /// Key was already defined in the same section
+    KeyAlreadyDefined {
+        /// Line number
+        line_no: usize,
+        /// Name of key
+        key: String,
+    },
+
+    /// No key in entry definition
+    NoKey {
+        /// Line number
+        line_no: usize,
+    },
+
+    /// No value in entry definition
+    NoValue {
+        /// Line number
+        line_no: usize,
+    },
+
+    /// Key is not known in this section
+    UnknownKey {
+        /// Line number
+        line_no: usize,
+        /// Name of key
+        key: String,
+    },
+
+    /// Value could not be used for this key
+    InvalidValue {
+        /// Line number
+        line_no: usize,
+        /// Name of key
+        key: String,
+    },
+
+    /// Required key is missing from section
+    MissingKey {
+        /// Line number of the section
+        line_no: usize,
+        /// Name of key
+        key: String,
+    },
+}
+
+/// Parse configuration file into sections.
+///
+/// Entries before the first section header are returned in a section with an empty name.
+/// Value is everything after the first `=`, so values can contain `=` themselves.
+pub fn parse_sections(file: &str) -> Result<Vec<Section<'_>>, ConfigError> {
+    let mut sections = vec![Section {
+        line_no: 0,
+        name: "",
+        entries: vec![],
+    }];
+
+    // Loop lines
+    for (line_no, line) in file.lines().enumerate() {
+        let line_no = line_no + 1;
+        let line = line.trim();
+
+        // Ignore blank lines and comments
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        // Section header
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header
+                .strip_suffix(']')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .ok_or(ConfigError::InvalidSection { line_no })?;
+
+            if sections.iter().any(|section| section.name == name) {
+                return Err(ConfigError::SectionAlreadyDefined {
+                    line_no,
+                    name: name.to_string(),
+                });
+            }
+
+            sections.push(Section {
+                line_no,
+                name,
+                entries: vec![],
+            });
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').unwrap_or((line, ""));
+
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(ConfigError::NoKey { line_no });
+        }
+
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(ConfigError::NoValue { line_no });
+        }
+
+        // Sections always contain at least the unnamed one
+        let section = sections.last_mut().unwrap();
+        if section.entries.iter().any(|entry| entry.key == key) {
+            return Err(ConfigError::KeyAlreadyDefined {
+                line_no,
+                key: key.to_string(),
+            });
+        }
+
+        section.entries.push(Entry {
+            line_no,
+            key,
+            value,
+        });
+    }
+
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sections_works() {
+        const FILE: &str = "# comment\n\
+            top=level\n\
+            \n\
+            [first]\n\
+            url = https://example.org/boards.js?json=1\n\
+            [ second ]\n\
+            key=value\n";
+
+        assert_eq!(
+            parse_sections(FILE),
+            Ok(vec![
+                Section {
+                    line_no: 0,
+                    name: "",
+                    entries: vec![Entry {
+                        line_no: 2,
+                        key: "top",
+                        value: "level"
+                    }],
+                },
+                Section {
+                    line_no: 4,
+                    name: "first",
+                    entries: vec![Entry {
+                        line_no: 5,
+                        key: "url",
+                        value: "https://example.org/boards.js?json=1"
+                    }],
+                },
+                Section {
+                    line_no: 6,
+                    name: "second",
+                    entries: vec![Entry {
+                        line_no: 7,
+                        key: "key",
+                        value: "value"
+                    }],
+                },
+            ])
+        );
+
+        use ConfigError::*;
+
+        assert_eq!(parse_sections("[first"), Err(InvalidSection { line_no: 1 }));
+        assert_eq!(parse_sections("[ ]"), Err(InvalidSection { line_no: 1 }));
+        assert_eq!(
+            parse_sections("[a]\n[a]"),
+            Err(SectionAlreadyDefined {
+                line_no: 2,
+                name: "a".to_string()
+            })
+        );
+        assert_eq!(
+            parse_sections("a=1\na=2"),
+            Err(KeyAlreadyDefined {
+                line_no: 2,
+                key: "a".to_string()
+            })
+        );
+        assert_eq!(parse_sections("a=1\n[b]\na=2").map(|s| s.len()), Ok(2));
+        assert_eq!(parse_sections("=1"), Err(NoKey { line_no: 1 }));
+        assert_eq!(parse_sections("a="), Err(NoValue { line_no: 1 }));
+        assert_eq!(parse_sections("a"), Err(NoValue { line_no: 1 }));
+    }
+}
@@ -6,8 +6,11 @@ use std::{io, thread};
 use termion::event::Key;
 use termion::input::TermRead;
 
+use crate::loader::Loaded;
+
 pub(crate) struct Events {
     rx: mpsc::Receiver<Event<Key>>,
+    tx: mpsc::Sender<Event<Key>>,
     _input_handle: thread::JoinHandle<()>,
     _ignore_exit_key: Arc<AtomicBool>,
     _tick_handle: thread::JoinHandle<()>,
@@ -16,6 +19,7 @@ pub(crate) struct Events {
 pub(crate) enum Event<I> {
     Input(I),
     Tick,
+    Loaded(Box<Loaded>),
 }
 
 impl Events {
@@ -44,6 +48,7 @@ impl Events {
         };
 
         let tick_handle = {
+            let tx = tx.clone();
             thread::spawn(move || loop {
                 if tx.send(Event::Tick).is_err() {
                     break;
@@ -54,6 +59,7 @@ impl Events {
 
         Events {
             rx,
+            tx,
             _ignore_exit_key: ignore_exit_key,
             _input_handle: input_handle,
             _tick_handle: tick_handle,
@@ -64,6 +70,11 @@ impl Events {
         self.rx.recv()
     }
 
+    /// Sender for events produced outside of the input and tick threads
+    pub(crate) fn sender(&self) -> mpsc::Sender<Event<Key>> {
+        self.tx.clone()
+    }
+
     pub fn _disable_exit_key(&mut self) {
         self._ignore_exit_key.store(true, Ordering::Relaxed);
     }
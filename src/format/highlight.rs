@@ -0,0 +1,255 @@
+//! Keyword-based syntax highlighting for code blocks.
+//!
+//! Lines are highlighted one at a time, so comments and strings spanning
+//! several lines are only highlighted on their first line.
+
+/// Languages that can be told apart by their keywords
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Language {
+    Rust,
+    Python,
+    /// C, C++, Java, JavaScript and alike
+    CLike,
+    Shell,
+    Plain,
+}
+
+/// Kind of highlighted token
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Token {
+    Plain,
+    Keyword,
+    String,
+    Number,
+    Comment,
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while",
+];
+
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "False", "finally", "for", "from", "global", "if", "import", "in", "is",
+    "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True", "try", "while",
+    "with", "yield",
+];
+
+// kept in rows, rustfmt puts long strings one per line
+#[rustfmt::skip]
+const C_LIKE_KEYWORDS: &[&str] = &[
+    "auto", "bool", "break", "case", "catch", "char", "class", "const", "continue", "default",
+    "delete", "do", "double", "else", "enum", "extern", "false", "final", "float", "for",
+    "function", "goto", "if", "import", "int", "let", "long", "new", "null", "private",
+    "protected", "public", "return", "short", "signed", "sizeof", "static", "struct", "switch",
+    "this", "throw", "true", "try", "typedef", "union", "unsigned", "var", "void", "volatile",
+    "while",
+];
+
+const SHELL_KEYWORDS: &[&str] = &[
+    "case", "do", "done", "echo", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+    "in", "local", "read", "return", "then", "until", "while",
+];
+
+impl Language {
+    /// Guess language from distinctive snippets, `Plain` if nothing matches
+    pub(crate) fn guess(code: &str) -> Self {
+        const HINTS: &[(Language, &[&str])] = &[
+            (
+                Language::Rust,
+                &["fn ", "let mut ", "impl ", "println!", "-> ", "::", "&mut "],
+            ),
+            (
+                Language::Python,
+                &[
+                    "def ", "import ", "elif ", "self.", "print(", "None", "):\n",
+                ],
+            ),
+            (
+                Language::Shell,
+                &["#!/bin/", "echo ", "fi\n", "done", "$(", "sudo ", "| grep"],
+            ),
+            (
+                Language::CLike,
+                &[
+                    "#include",
+                    "int main",
+                    "printf(",
+                    "void ",
+                    "public ",
+                    "function ",
+                    "};",
+                ],
+            ),
+        ];
+
+        HINTS
+            .iter()
+            .map(|(language, hints)| {
+                let score = hints.iter().filter(|hint| code.contains(*hint)).count();
+                (score, *language)
+            })
+            .filter(|(score, _)| *score > 0)
+            // first language wins ties
+            .rev()
+            .max_by_key(|(score, _)| *score)
+            .map_or(Self::Plain, |(_, language)| language)
+    }
+
+    fn keywords(&self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => RUST_KEYWORDS,
+            Self::Python => PYTHON_KEYWORDS,
+            Self::CLike => C_LIKE_KEYWORDS,
+            Self::Shell => SHELL_KEYWORDS,
+            Self::Plain => &[],
+        }
+    }
+
+    fn line_comment(&self) -> Option<&'static str> {
+        match self {
+            Self::Rust | Self::CLike => Some("//"),
+            Self::Python | Self::Shell => Some("#"),
+            Self::Plain => None,
+        }
+    }
+}
+
+/// Split line into highlighted tokens, joined they give the line back
+pub(crate) fn highlight_line(line: &str, language: Language) -> Vec<(String, Token)> {
+    let mut tokens: Vec<(String, Token)> = vec![];
+    let mut push = |text: &str, token: Token| match tokens.last_mut() {
+        Some((last, last_token)) if *last_token == token => last.push_str(text),
+        _ => tokens.push((text.to_string(), token)),
+    };
+
+    if language == Language::Plain {
+        push(line, Token::Plain);
+        return tokens;
+    }
+
+    let mut rest = line;
+    while let Some(ch) = rest.chars().next() {
+        if language
+            .line_comment()
+            .is_some_and(|comment| rest.starts_with(comment))
+            || (language == Language::CLike && rest.starts_with("/*"))
+        {
+            push(rest, Token::Comment);
+            break;
+        }
+
+        let len = if ch == '"' || ch == '\'' || (ch == '`' && language != Language::Rust) {
+            // Rust lifetimes and chars share the quote, only close on the same line
+            let end = string_end(&rest[1..], ch).map_or(rest.len(), |end| end + 2);
+            if ch == '\'' && language == Language::Rust && end > 4 {
+                push(&rest[..1], Token::Plain);
+                1
+            } else {
+                push(&rest[..end], Token::String);
+                end
+            }
+        } else if ch.is_ascii_digit() {
+            let end = rest
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '.' || ch == '_'))
+                .unwrap_or(rest.len());
+            push(&rest[..end], Token::Number);
+            end
+        } else if ch.is_alphabetic() || ch == '_' {
+            let end = rest
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let token = if language.keywords().contains(&word) {
+                Token::Keyword
+            } else {
+                Token::Plain
+            };
+            push(word, token);
+            end
+        } else {
+            push(&rest[..ch.len_utf8()], Token::Plain);
+            ch.len_utf8()
+        };
+
+        rest = &rest[len..];
+    }
+
+    tokens
+}
+
+/// Byte position of the closing quote, skipping escaped ones
+fn string_end(string: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, ch) in string.char_indices() {
+        match ch {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if ch == quote => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_language() {
+        assert_eq!(
+            Language::guess("fn main() {\n    let mut x = 1;\n}"),
+            Language::Rust
+        );
+        assert_eq!(
+            Language::guess("def f(x):\n    return None"),
+            Language::Python
+        );
+        assert_eq!(
+            Language::guess("#include <stdio.h>\nint main() {}"),
+            Language::CLike
+        );
+        assert_eq!(Language::guess("#!/bin/sh\necho $(pwd)"), Language::Shell);
+        assert_eq!(Language::guess("hello world"), Language::Plain);
+    }
+
+    #[test]
+    fn test_highlight_line() {
+        assert_eq!(
+            highlight_line("let s = \"a \\\" b\"; // done", Language::Rust),
+            vec![
+                ("let".to_string(), Token::Keyword),
+                (" s = ".to_string(), Token::Plain),
+                ("\"a \\\" b\"".to_string(), Token::String),
+                ("; ".to_string(), Token::Plain),
+                ("// done".to_string(), Token::Comment),
+            ]
+        );
+        assert_eq!(
+            highlight_line("fn f<'a>(x: &'a str) -> u8 { 42 }", Language::Rust)
+                .into_iter()
+                .filter(|(_, token)| *token != Token::Plain)
+                .collect::<Vec<_>>(),
+            vec![
+                ("fn".to_string(), Token::Keyword),
+                ("42".to_string(), Token::Number),
+            ]
+        );
+        assert_eq!(
+            highlight_line("x = 'unclosed", Language::Python),
+            vec![
+                ("x = ".to_string(), Token::Plain),
+                ("'unclosed".to_string(), Token::String),
+            ]
+        );
+        assert_eq!(
+            highlight_line("if x", Language::Plain),
+            vec![("if x".to_string(), Token::Plain)]
+        );
+    }
+}
@@ -0,0 +1,753 @@
+//! Parser for the HTML subset used in post comments
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::sanitize::sanitize;
+
+/// Elements that never have children
+const VOID_ELEMENTS: [&str; 6] = ["br", "wbr", "img", "hr", "input", "meta"];
+
+/// Node of the parsed comment tree
+#[derive(Debug, PartialEq)]
+pub(crate) enum Node {
+    /// Text with entities decoded
+    Text(String),
+    Element(Element),
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Element {
+    /// Lowercase tag name
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes: vec![],
+            children: vec![],
+        }
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn has_class(&self, class: &str) -> bool {
+        self.attribute("class")
+            .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+    }
+
+    pub(crate) fn children(&self) -> &[Node] {
+        &self.children
+    }
+}
+
+/// Inline markup of a piece of text, from the elements it is nested in
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Markup {
+    /// Greentext, `<span class="quote">`
+    pub(crate) quote: bool,
+    /// Link to another post, `<a class="quotelink">`
+    pub(crate) quote_link: bool,
+    /// Number of the linked post, for links within the thread
+    pub(crate) post_link: Option<usize>,
+    /// Link to a post in another thread
+    pub(crate) cross_link: bool,
+    /// Link to a post that no longer exists, `<span class="deadlink">`
+    pub(crate) dead_link: bool,
+    /// `<s>`
+    pub(crate) spoiler: bool,
+    pub(crate) bold: bool,
+    pub(crate) italic: bool,
+    pub(crate) underline: bool,
+    /// `<pre class="prettyprint">`
+    pub(crate) code: bool,
+    /// Rows of the EXIF table attached to some images
+    pub(crate) exif: bool,
+}
+
+/// Piece of text with the same markup
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Segment {
+    pub(crate) text: String,
+    pub(crate) markup: Markup,
+}
+
+impl Segment {
+    pub(crate) fn new(text: &str, markup: Markup) -> Self {
+        Self {
+            text: text.to_string(),
+            markup,
+        }
+    }
+}
+
+/// Target of a quotelink
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Link {
+    /// Post in the same thread, `#p123`
+    Post(usize),
+    /// Another thread or a post in it, `/g/thread/123#p456`.
+    ///
+    /// Board is `None` for relative links within the same board.
+    Thread {
+        board: Option<String>,
+        thread: usize,
+        post: Option<usize>,
+    },
+    /// Index of a board, `//boards.4chan.org/sci/`
+    Board(String),
+}
+
+impl Link {
+    /// Parse the `href` of a quotelink, `None` if it does not point to a post or a board
+    pub(crate) fn parse(href: &str) -> Option<Self> {
+        // drop the scheme and host of absolute links
+        let path = match href.find("//") {
+            Some(i) => href[i + 2..].find('/').map_or("", |j| &href[i + 2 + j..]),
+            None => href,
+        };
+
+        let (path, fragment) = path.split_once('#').unwrap_or((path, ""));
+        let post = fragment
+            .strip_prefix('p')
+            .or(fragment.strip_prefix('q'))
+            .and_then(|no| no.parse().ok());
+        if path.is_empty() {
+            return post.map(Self::Post);
+        }
+
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let (board, thread) = match parts.as_slice() {
+            ["thread" | "res", thread, ..] => (None, thread),
+            [board, "thread" | "res", thread, ..] => (Some(board.to_string()), thread),
+            // board indexes end with a slash, unlike pages such as `/rules`
+            [board] if is_board_name(board) && path.ends_with('/') => {
+                return Some(Self::Board(board.to_string()))
+            }
+            [board, "catalog"] if is_board_name(board) => {
+                return Some(Self::Board(board.to_string()))
+            }
+            _ => return None,
+        };
+        let thread = thread.trim_end_matches(".html").parse().ok()?;
+
+        Some(Self::Thread {
+            board,
+            thread,
+            post,
+        })
+    }
+}
+
+fn is_board_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|ch| ch.is_ascii_alphanumeric())
+}
+
+/// Cross-board references written as text, `>>>/g/` and `>>>/g/123`.
+///
+/// The thread of a post is not known from the text, so the number is taken as a thread.
+fn text_links(text: &str) -> Vec<Link> {
+    static BOARD_LINK: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r">>>/([a-zA-Z0-9]+)/(\d+)?").expect("Board link regex must be valid")
+    });
+
+    BOARD_LINK
+        .captures_iter(text)
+        .map(|captures| {
+            let board = captures[1].to_string();
+            match captures.get(2).and_then(|no| no.as_str().parse().ok()) {
+                Some(thread) => Link::Thread {
+                    board: Some(board),
+                    thread,
+                    post: None,
+                },
+                None => Link::Board(board),
+            }
+        })
+        .collect()
+}
+
+/// Parse comment HTML into a tree.
+///
+/// Parsing never fails: unclosed elements are closed at the end,
+/// stray closing tags are ignored and malformed tags are kept as text.
+pub(crate) fn parse(html: &str) -> Vec<Node> {
+    let mut stack = vec![Element::new("")];
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            push_text(&mut stack, rest);
+            break;
+        };
+        push_text(&mut stack, &rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        // not a tag, e.g. `<3`
+        if !rest[1..].starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '/') {
+            push_text(&mut stack, "<");
+            rest = &rest[1..];
+            continue;
+        }
+
+        let Some(end) = find_tag_end(rest) else {
+            push_text(&mut stack, rest);
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            close_element(&mut stack, &name.trim().to_lowercase());
+            continue;
+        }
+
+        let Some(element) = parse_tag(tag) else {
+            push_text(&mut stack, &format!("<{}>", tag));
+            continue;
+        };
+
+        if tag.ends_with('/') || VOID_ELEMENTS.contains(&element.name.as_str()) {
+            push_node(&mut stack, Node::Element(element));
+        } else {
+            stack.push(element);
+        }
+    }
+
+    while stack.len() > 1 {
+        pop_element(&mut stack);
+    }
+
+    stack.pop().map_or(vec![], |root| root.children)
+}
+
+/// Flatten the tree into lines of styled segments
+pub(crate) fn to_lines(nodes: &[Node]) -> Vec<Vec<Segment>> {
+    let mut lines = vec![vec![]];
+    push_lines(nodes, Markup::default(), &mut lines);
+
+    while lines.len() > 1 && lines.last().is_some_and(Vec::is_empty) {
+        lines.pop();
+    }
+
+    lines
+}
+
+/// Text of every `<pre>` block, lines joined with newlines
+pub(crate) fn code_blocks(nodes: &[Node]) -> Vec<String> {
+    let mut blocks = vec![];
+    for node in nodes {
+        let Node::Element(element) = node else {
+            continue;
+        };
+
+        if element.name() == "pre" {
+            let lines: Vec<String> = to_lines(element.children())
+                .iter()
+                .map(|line| line.iter().map(|segment| segment.text.as_str()).collect())
+                .collect();
+            blocks.push(lines.join("\n"));
+        } else {
+            blocks.extend(code_blocks(element.children()));
+        }
+    }
+
+    blocks
+}
+
+/// Targets of the quotelinks and of the board links in the text, in order of appearance and without duplicates
+pub(crate) fn links(nodes: &[Node]) -> Vec<Link> {
+    fn push(link: Link, links: &mut Vec<Link>) {
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+
+    fn collect(nodes: &[Node], links: &mut Vec<Link>) {
+        for node in nodes {
+            let element = match node {
+                Node::Text(text) => {
+                    for link in text_links(text) {
+                        push(link, links);
+                    }
+                    continue;
+                }
+                Node::Element(element) => element,
+            };
+
+            if element.name() == "a" && element.has_class("quotelink") {
+                if let Some(link) = element.attribute("href").and_then(Link::parse) {
+                    push(link, links);
+                }
+            } else if !element.has_class("deadlink") {
+                collect(element.children(), links);
+            }
+        }
+    }
+
+    let mut links = vec![];
+    collect(nodes, &mut links);
+
+    links
+}
+
+fn push_lines(nodes: &[Node], markup: Markup, lines: &mut Vec<Vec<Segment>>) {
+    for node in nodes {
+        let element = match node {
+            Node::Text(text) => {
+                push_segment_text(text, markup, lines);
+                continue;
+            }
+            Node::Element(element) => element,
+        };
+
+        let mut markup = markup;
+        match element.name() {
+            "br" => {
+                lines.push(vec![]);
+                continue;
+            }
+            // site controls, such as the EXIF toggle or "Comment too long"
+            "span" if element.has_class("abbr") => continue,
+            "script" | "style" | "wbr" => continue,
+            "span" if element.has_class("quote") => markup.quote = true,
+            "span" if element.has_class("deadlink") => markup.dead_link = true,
+            "a" if element.has_class("quotelink") => {
+                markup.quote_link = true;
+                match element.attribute("href").and_then(Link::parse) {
+                    Some(Link::Post(no)) => markup.post_link = Some(no),
+                    Some(Link::Thread { .. } | Link::Board(_)) => markup.cross_link = true,
+                    None => {}
+                }
+            }
+            "s" => markup.spoiler = true,
+            "b" | "strong" => markup.bold = true,
+            "i" | "em" => markup.italic = true,
+            "u" => markup.underline = true,
+            "pre" => {
+                markup.code = true;
+                start_line(lines);
+            }
+            "table" if element.has_class("exif") => {
+                markup.exif = true;
+                start_line(lines);
+            }
+            // every row on its own line, cells separated by a colon
+            "tr" => start_line(lines),
+            "td" | "th" if lines.last().is_some_and(|line| !line.is_empty()) => {
+                push_segment_text(": ", markup, lines);
+            }
+            _ => {}
+        }
+
+        push_lines(element.children(), markup, lines);
+
+        if matches!(element.name(), "pre" | "table" | "tr") {
+            start_line(lines);
+        }
+    }
+}
+
+/// Start a new line, unless the current one is still empty
+fn start_line(lines: &mut Vec<Vec<Segment>>) {
+    if lines.last().is_some_and(|line| !line.is_empty()) {
+        lines.push(vec![]);
+    }
+}
+
+/// Append text to the last line, code keeps its line breaks
+fn push_segment_text(text: &str, markup: Markup, lines: &mut Vec<Vec<Segment>>) {
+    let mut pieces = if markup.code {
+        text.split('\n').map(str::to_string).collect()
+    } else {
+        vec![text.replace(['\n', '\t'], " ")]
+    };
+
+    let last = pieces.pop().unwrap_or_default();
+    for piece in pieces {
+        push_segment(&piece, markup, lines);
+        lines.push(vec![]);
+    }
+    push_segment(&last, markup, lines);
+}
+
+/// Append segment to the last line, merging it with the previous one of the same markup
+fn push_segment(text: &str, markup: Markup, lines: &mut [Vec<Segment>]) {
+    if text.is_empty() {
+        return;
+    }
+
+    // There is always at least one line
+    let line = lines.last_mut().unwrap();
+    match line.last_mut() {
+        Some(segment) if segment.markup == markup => segment.text.push_str(text),
+        _ => line.push(Segment::new(text, markup)),
+    }
+}
+
+/// Position of the `>` closing the tag, ignoring those in quoted attribute values
+fn find_tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, ch) in tag.char_indices() {
+        match (quote, ch) {
+            (None, '"' | '\'') => quote = Some(ch),
+            (Some(open), _) if open == ch => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Parse opening tag contents, `None` if the tag name is not valid
+fn parse_tag(tag: &str) -> Option<Element> {
+    let tag = tag.trim_end_matches('/');
+    let name_len = tag
+        .find(|ch: char| ch.is_whitespace() || ch == '/')
+        .unwrap_or(tag.len());
+    let name = &tag[..name_len];
+
+    if !name.starts_with(|ch: char| ch.is_ascii_alphabetic())
+        || !name.chars().all(|ch| ch.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+
+    let mut element = Element::new(&name.to_lowercase());
+    let mut rest = tag[name_len..].trim_start();
+
+    while !rest.is_empty() {
+        let key_len = rest
+            .find(|ch: char| ch.is_whitespace() || ch == '=')
+            .unwrap_or(rest.len());
+        let key = rest[..key_len].to_lowercase();
+        rest = rest[key_len..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(value) => {
+                let value = value.trim_start();
+                let (parsed, remaining) = match value.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = value[1..].find(quote).map_or(value.len(), |end| end + 1);
+                        (&value[1..end], value.get(end + 1..).unwrap_or(""))
+                    }
+                    _ => {
+                        let end = value.find(char::is_whitespace).unwrap_or(value.len());
+                        (&value[..end], &value[end..])
+                    }
+                };
+                rest = remaining.trim_start();
+                decode(parsed)
+            }
+            None => "".to_string(),
+        };
+
+        if !key.is_empty() {
+            element.attributes.push((key, value));
+        }
+    }
+
+    Some(element)
+}
+
+/// Decode entities, sanitizing the decoded text
+fn decode(text: &str) -> String {
+    sanitize(&htmlescape::decode_html(text).unwrap_or_else(|_| text.to_string()))
+}
+
+/// Append text to the open element, merging it with the preceding text
+fn push_text(stack: &mut [Element], text: &str) {
+    if text.is_empty() {
+        return;
+    }
+
+    // Stack always contains at least the root
+    let children = &mut stack.last_mut().unwrap().children;
+    match children.last_mut() {
+        Some(Node::Text(last)) => last.push_str(&decode(text)),
+        _ => children.push(Node::Text(decode(text))),
+    }
+}
+
+fn push_node(stack: &mut [Element], node: Node) {
+    // Stack always contains at least the root
+    stack.last_mut().unwrap().children.push(node);
+}
+
+/// Close the innermost open element with the name, and everything opened inside it
+fn close_element(stack: &mut Vec<Element>, name: &str) {
+    let Some(position) = stack
+        .iter()
+        .skip(1)
+        .rposition(|element| element.name == name)
+    else {
+        return;
+    };
+
+    while stack.len() > position + 1 {
+        pop_element(stack);
+    }
+}
+
+fn pop_element(stack: &mut Vec<Element>) {
+    if let Some(element) = stack.pop() {
+        push_node(stack, Node::Element(element));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Node {
+        Node::Text(text.to_string())
+    }
+
+    fn element(name: &str, attributes: &[(&str, &str)], children: Vec<Node>) -> Node {
+        Node::Element(Element {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            children,
+        })
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(r##"<a href="#p123" class="quotelink">&gt;&gt;123</a><br>yes &amp; no"##),
+            vec![
+                element(
+                    "a",
+                    &[("href", "#p123"), ("class", "quotelink")],
+                    vec![text(">>123")]
+                ),
+                element("br", &[], vec![]),
+                text("yes & no"),
+            ]
+        );
+
+        // unclosed elements are closed at the end, stray closing tags are ignored
+        assert_eq!(
+            parse("<b>bold <i>both</b> none</u>"),
+            vec![
+                element(
+                    "b",
+                    &[],
+                    vec![text("bold "), element("i", &[], vec![text("both")])]
+                ),
+                text(" none"),
+            ]
+        );
+
+        // malformed tags and comments
+        assert_eq!(
+            parse("i <3 you <!-- hidden --> a<b"),
+            vec![text("i <3 you  a<b")]
+        );
+        assert_eq!(
+            parse(r#"<span title='a > b' data-x=1 hidden>x</span>"#),
+            vec![element(
+                "span",
+                &[("title", "a > b"), ("data-x", "1"), ("hidden", "")],
+                vec![text("x")]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_to_lines() {
+        let quote = Markup {
+            quote: true,
+            ..Markup::default()
+        };
+        let quote_link = Markup {
+            quote_link: true,
+            post_link: Some(1),
+            ..Markup::default()
+        };
+        let spoiler = Markup {
+            spoiler: true,
+            ..Markup::default()
+        };
+
+        let lines = to_lines(&parse(
+            r##"<a href="#p1" class="quotelink">&gt;&gt;1</a><br><span class="quote">&gt;be me<s>pls</s></span><br><br>a<wbr>b"##,
+        ));
+        assert_eq!(
+            lines,
+            vec![
+                vec![Segment::new(">>1", quote_link)],
+                vec![
+                    Segment::new(">be me", quote),
+                    Segment::new(
+                        "pls",
+                        Markup {
+                            quote: true,
+                            ..spoiler
+                        }
+                    ),
+                ],
+                vec![],
+                vec![Segment::new("ab", Markup::default())],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_lines_code_and_exif() {
+        let code = Markup {
+            code: true,
+            ..Markup::default()
+        };
+        let exif = Markup {
+            exif: true,
+            ..Markup::default()
+        };
+
+        let lines = to_lines(&parse(
+            "see<pre class=\"prettyprint\">fn main() {<br>    x &lt; y\n}</pre>done",
+        ));
+        assert_eq!(
+            lines,
+            vec![
+                vec![Segment::new("see", Markup::default())],
+                vec![Segment::new("fn main() {", code)],
+                vec![Segment::new("    x < y", code)],
+                vec![Segment::new("}", code)],
+                vec![Segment::new("done", Markup::default())],
+            ]
+        );
+
+        let lines = to_lines(&parse(
+            "pic<br><br><span class=\"abbr\">[EXIF data available. Click \
+            <a href=\"javascript:void(0)\">here</a> to show/hide.]</span><br>\
+            <table class=\"exif\" id=\"exif1\"><tr><td colspan=\"2\"><b></b></td></tr>\
+            <tr><td>Camera Model</td><td>iPhone</td></tr></table>",
+        ));
+        assert_eq!(
+            lines,
+            vec![
+                vec![Segment::new("pic", Markup::default())],
+                vec![],
+                vec![],
+                vec![Segment::new("Camera Model: iPhone", exif)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_link() {
+        assert_eq!(Link::parse("#p123"), Some(Link::Post(123)));
+        assert_eq!(
+            Link::parse("/g/thread/100#p123"),
+            Some(Link::Thread {
+                board: Some("g".to_string()),
+                thread: 100,
+                post: Some(123)
+            })
+        );
+        assert_eq!(
+            Link::parse("thread/100"),
+            Some(Link::Thread {
+                board: None,
+                thread: 100,
+                post: None
+            })
+        );
+        assert_eq!(
+            Link::parse("https://boards.4chan.org/sci/thread/7/some-title#p8"),
+            Some(Link::Thread {
+                board: Some("sci".to_string()),
+                thread: 7,
+                post: Some(8)
+            })
+        );
+        assert_eq!(
+            Link::parse("/b/res/55.html#q56"),
+            Some(Link::Thread {
+                board: Some("b".to_string()),
+                thread: 55,
+                post: Some(56)
+            })
+        );
+        assert_eq!(
+            Link::parse("//boards.4chan.org/sci/"),
+            Some(Link::Board("sci".to_string()))
+        );
+        assert_eq!(
+            Link::parse("/g/catalog#s=rust"),
+            Some(Link::Board("g".to_string()))
+        );
+        assert_eq!(Link::parse("//boards.4chan.org/"), None);
+        assert_eq!(Link::parse("/rules#g"), None);
+        assert_eq!(Link::parse("#top"), None);
+    }
+
+    #[test]
+    fn test_links() {
+        let nodes = parse(
+            "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a> \
+            <span class=\"quote\"><a href=\"/g/thread/5#p6\" class=\"quotelink\">&gt;&gt;6</a></span> \
+            <a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a> <a href=\"#p2\">plain link</a>",
+        );
+        assert_eq!(
+            links(&nodes),
+            vec![
+                Link::Post(1),
+                Link::Thread {
+                    board: Some("g".to_string()),
+                    thread: 5,
+                    post: Some(6)
+                },
+            ]
+        );
+
+        let lines = to_lines(&nodes);
+        assert_eq!(lines[0][0].markup.post_link, Some(1));
+        assert!(lines[0][2].markup.cross_link);
+    }
+
+    #[test]
+    fn test_board_links() {
+        let nodes = parse(
+            "<a href=\"//boards.4chan.org/sci/\" class=\"quotelink\">&gt;&gt;&gt;/sci/</a><br>\
+            see &gt;&gt;&gt;/g/123 and &gt;&gt;&gt;/sci/ again<br>\
+            <span class=\"deadlink\">&gt;&gt;&gt;/v/456</span>",
+        );
+        assert_eq!(
+            links(&nodes),
+            vec![
+                Link::Board("sci".to_string()),
+                Link::Thread {
+                    board: Some("g".to_string()),
+                    thread: 123,
+                    post: None
+                },
+            ]
+        );
+        assert!(to_lines(&nodes)[0][0].markup.cross_link);
+    }
+}
@@ -0,0 +1,948 @@
+use std::collections::HashMap;
+
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans, Text};
+use tui::widgets::ListItem;
+
+use self::highlight::{highlight_line, Language, Token};
+use self::html::{Markup, Segment};
+use self::sanitize::sanitize_line;
+use self::time::Clock;
+use self::wrap::{cut_row, text_width, truncate_width, wrap_segments};
+use crate::model::{Board, Thread, ThreadPost};
+
+pub(crate) use self::html::Link;
+
+mod highlight;
+mod html;
+mod sanitize;
+pub(crate) mod time;
+mod wrap;
+
+pub(crate) fn format_default(str: &str) -> String {
+    format!(" {}", str)
+}
+
+/// Decode entities of a single line of text from the API, sanitized for the terminal
+pub(crate) fn format_html(str: &str) -> String {
+    sanitize_line(&htmlescape::decode_html(str).unwrap_or_else(|_| str.to_string()))
+}
+
+/// Sanitize a single line of text from the API, which is not HTML-encoded
+pub(crate) fn format_text(str: &str) -> String {
+    sanitize_line(str)
+}
+
+/// How a single post is shown
+#[derive(Clone, Copy)]
+pub(crate) struct PostDisplay<'a> {
+    reveal_spoilers: bool,
+    /// Comment lines shown before the post is cut, `None` to show the whole post
+    line_limit: Option<usize>,
+    /// Posts of the loaded thread by number, links to other posts are shown as dead
+    thread_posts: Option<&'a HashMap<usize, usize>>,
+    /// Numbers of the posts replying to the post
+    replies: &'a [usize],
+    /// Depth of the post in the thread tree, shown as indentation guides
+    indent: usize,
+    /// Number of replies hidden under the post in the thread tree
+    collapsed: usize,
+}
+
+impl<'a> PostDisplay<'a> {
+    pub(crate) fn new(reveal_spoilers: bool, line_limit: Option<usize>) -> Self {
+        Self {
+            reveal_spoilers,
+            line_limit,
+            thread_posts: None,
+            replies: &[],
+            indent: 0,
+            collapsed: 0,
+        }
+    }
+
+    pub(crate) fn with_thread_posts(self, thread_posts: &'a HashMap<usize, usize>) -> Self {
+        Self {
+            thread_posts: Some(thread_posts),
+            ..self
+        }
+    }
+
+    pub(crate) fn with_replies(self, replies: &'a [usize]) -> Self {
+        Self { replies, ..self }
+    }
+
+    /// Position of the post in the thread tree
+    pub(crate) fn with_tree(self, indent: usize, collapsed: usize) -> Self {
+        Self {
+            indent,
+            collapsed,
+            ..self
+        }
+    }
+}
+
+/// Thread in the thread list, its OP with the reply and image counts
+pub(crate) fn format_post_short(
+    thread: &Thread,
+    no: usize,
+    len: usize,
+    display: PostDisplay,
+    clock: Clock,
+    thread_ages: bool,
+    area: Rect,
+) -> ListItem<'static> {
+    let op = thread.posts().first().unwrap();
+    let mut lines = format_post(
+        op,
+        format!("{}/{}", no, len),
+        thread.dead(),
+        display,
+        clock,
+        area,
+    );
+
+    let mut footer = format!("{} Replies, {} Images", op.replies(), op.images());
+    if thread_ages && !op.stub() {
+        footer.push_str(&format!(", started {}", clock.age(op.time())));
+        if let [_, .., reply] = thread.posts() {
+            footer.push_str(&format!(", last reply {}", clock.age(reply.time())));
+        }
+    }
+
+    lines.push(Spans::from(Span::styled(
+        format_default(&footer),
+        Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::ITALIC),
+    )));
+    lines.push(Spans::from(""));
+
+    ListItem::new(Text::from(lines)).style(Style::default())
+}
+
+pub(crate) fn format_post_full(
+    post: &ThreadPost,
+    no: usize,
+    display: PostDisplay,
+    clock: Clock,
+    area: Rect,
+) -> ListItem<'static> {
+    // indentation takes at most half of the area
+    let guide_width = text_width(INDENT_GUIDE);
+    let levels = display.indent.min(area.width as usize / 2 / guide_width);
+    let area = Rect {
+        width: area.width - (levels * guide_width) as u16,
+        ..area
+    };
+
+    let mut lines = format_post(post, format!("#{}", no), false, display, clock, area);
+    if display.collapsed > 0 {
+        lines.push(Spans::from(Span::styled(
+            format_default(&format!(
+                "[+{} {} collapsed]",
+                display.collapsed,
+                if display.collapsed == 1 {
+                    "reply"
+                } else {
+                    "replies"
+                }
+            )),
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        )));
+    }
+    lines.push(Spans::from(""));
+
+    if levels > 0 {
+        let guides = INDENT_GUIDE.repeat(levels);
+        for line in &mut lines {
+            line.0.insert(
+                0,
+                Span::styled(guides.clone(), Style::default().fg(Color::DarkGray)),
+            );
+        }
+    }
+
+    ListItem::new(Text::from(lines)).style(Style::default())
+}
+
+/// Guide of one level of indentation in the thread tree
+const INDENT_GUIDE: &str = "│ ";
+
+/// Whole post for the reader overlay, wrapped to fit the `area` of the overlay
+pub(crate) fn format_post_reader(
+    post: &ThreadPost,
+    reveal_spoilers: bool,
+    clock: Clock,
+    area: Rect,
+) -> Vec<Spans<'static>> {
+    format_post(
+        post,
+        "".to_string(),
+        false,
+        PostDisplay::new(reveal_spoilers, None),
+        clock,
+        area,
+    )
+}
+
+const CUT_MSG: &str = "[...]";
+const CUT_MSG_LEN: usize = CUT_MSG.len();
+/// Marks code lines cut at the panel width
+const CODE_CUT_MSG: &str = "»";
+
+const SPOILER_BLOCK: &str = "█";
+
+/// Header, file and comment lines of the post
+fn format_post(
+    post: &ThreadPost,
+    no: String,
+    dead: bool,
+    display: PostDisplay,
+    clock: Clock,
+    area: Rect,
+) -> Vec<Spans<'static>> {
+    let reveal_spoilers = display.reveal_spoilers;
+    let mut lines = vec![Spans::from("")];
+    let mut header: Vec<Span> = vec![];
+
+    if !post.sub().is_empty() {
+        header.push(Span::styled(
+            format_default(&format_html(post.sub())),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    let author_style = Style::default().add_modifier(Modifier::ITALIC | Modifier::UNDERLINED);
+    header.push(Span::raw(" "));
+    if post.stub() {
+        header.push(Span::styled(format!("No.{}", post.no()), author_style));
+    } else {
+        header.push(Span::styled(format_html(post.name()), author_style));
+
+        if let Some(trip) = post.trip() {
+            header.push(Span::styled(
+                format_text(trip),
+                author_style.fg(Color::Green),
+            ));
+        }
+
+        if let Some(capcode) = post.capcode() {
+            let (label, color) = format_capcode(capcode);
+            header.push(Span::styled(
+                format_default(&label),
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            ));
+        }
+
+        if let Some(flag) = post.country().or(post.board_flag()) {
+            header.push(Span::styled(
+                format_default(&format!(
+                    "[{}]",
+                    format_text(post.flag_name().unwrap_or(flag))
+                )),
+                Style::default().fg(Color::Cyan),
+            ));
+        }
+
+        header.push(Span::styled(
+            format!(" {} No.{}", clock.time(post.time()), post.no()),
+            author_style,
+        ));
+    }
+
+    if !no.is_empty() {
+        header.push(Span::styled(
+            format_default(&no),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    if !display.replies.is_empty() {
+        let count = display.replies.len();
+        header.push(Span::styled(
+            format_default(&format!(
+                "[{} {}]",
+                count,
+                if count == 1 { "reply" } else { "replies" }
+            )),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+
+    if post.sticky() == 1 {
+        header.push(Span::styled(format_default("📌"), Style::default()));
+    }
+
+    if post.closed() == 1 {
+        header.push(Span::styled(format_default("🔓"), Style::default()));
+    }
+
+    if post.archived() == 1 {
+        header.push(Span::styled(
+            format_default("[Archived]"),
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+
+    if post.bumplimit() == 1 {
+        header.push(Span::styled(
+            format_default("[Bump limit]"),
+            Style::default().fg(Color::LightRed),
+        ));
+    }
+
+    if post.imagelimit() == 1 {
+        header.push(Span::styled(
+            format_default("[Image limit]"),
+            Style::default().fg(Color::LightRed),
+        ));
+    }
+
+    if dead {
+        header.push(Span::styled(format_default("💀"), Style::default()));
+    }
+
+    lines.push(Spans::from(header));
+
+    if post.stub() {
+        return lines;
+    }
+
+    if post.filedeleted() == 1 {
+        lines.push(Spans::from(Span::styled(
+            format_default("[File deleted]"),
+            Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::ITALIC),
+        )));
+    } else if post.filename().is_some() && post.ext().is_some() {
+        let filename = if post.spoiler() == 1 && !reveal_spoilers {
+            "Spoiler image".to_string()
+        } else {
+            format_text(&format!(
+                "{}{}",
+                post.filename().as_ref().unwrap(),
+                post.ext().as_ref().unwrap()
+            ))
+        };
+
+        lines.push(Spans::from(Span::styled(
+            format_default(&format!(
+                "{}{}",
+                filename,
+                format_file_info(post.fsize(), post.w(), post.h()),
+            )),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::ITALIC),
+        )));
+    }
+
+    lines.extend(format_post_contents(
+        post.com(),
+        calc_width(area) as usize,
+        display,
+    ));
+    lines.extend(format_backlinks(calc_width(area) as usize, display));
+
+    lines
+}
+
+/// `Replies: >>1 >>2` line of the posts replying to the post, wrapped to `sub_len` cells
+fn format_backlinks(sub_len: usize, display: PostDisplay) -> Vec<Spans<'static>> {
+    if display.replies.is_empty() {
+        return vec![];
+    }
+
+    let mut line = vec![Segment::new("Replies:", Markup::default())];
+    for no in display.replies {
+        line.push(Segment::new(" ", Markup::default()));
+        line.push(Segment::new(
+            &format!(">>{}", no),
+            Markup {
+                quote_link: true,
+                post_link: Some(*no),
+                ..Markup::default()
+            },
+        ));
+    }
+
+    wrap_segments(line, sub_len)
+        .into_iter()
+        .map(|row| Spans::from(row_spans(row, display)))
+        .collect()
+}
+
+/// Comment lines, wrapped to `sub_len` cells and cut after the line limit of `display`
+fn format_post_contents(string: &str, sub_len: usize, display: PostDisplay) -> Vec<Spans<'static>> {
+    let line_limit = display.line_limit.unwrap_or(usize::MAX);
+    let lines = html::to_lines(&html::parse(string));
+    let languages = code_languages(&lines);
+
+    let mut spans = Vec::with_capacity(lines.len().min(line_limit.saturating_add(1)));
+    let mut i = 0;
+
+    'line_loop: for (line, language) in lines.into_iter().zip(languages) {
+        if let Some(language) = language {
+            let text = line_text(&line);
+            if i >= line_limit {
+                let mut row = code_spans(&text, language, sub_len.saturating_sub(CUT_MSG_LEN));
+                row.push(Span::styled(CUT_MSG, Style::default().fg(Color::Magenta)));
+                spans.push(Spans::from(row));
+                break;
+            }
+
+            spans.push(Spans::from(code_spans(&text, language, sub_len)));
+            i += 1;
+            continue;
+        }
+
+        if line.is_empty() {
+            spans.push(Spans::from(""));
+
+            i += 1;
+
+            if i >= line_limit {
+                break;
+            }
+        }
+
+        for mut row in wrap_segments(line, sub_len) {
+            if i >= line_limit {
+                cut_row(&mut row, sub_len.saturating_sub(CUT_MSG_LEN));
+                let mut row = row_spans(row, display);
+                row.push(Span::styled(CUT_MSG, Style::default().fg(Color::Magenta)));
+                spans.push(Spans::from(row));
+                break 'line_loop;
+            }
+
+            spans.push(Spans::from(row_spans(row, display)));
+            i += 1;
+        }
+    }
+
+    spans
+}
+
+fn is_code(line: &[Segment]) -> bool {
+    line.first().is_some_and(|segment| segment.markup.code)
+}
+
+fn line_text(line: &[Segment]) -> String {
+    line.iter().map(|segment| segment.text.as_str()).collect()
+}
+
+/// Language of each code line, guessed once for every code block
+fn code_languages(lines: &[Vec<Segment>]) -> Vec<Option<Language>> {
+    let mut languages = vec![None; lines.len()];
+    let mut i = 0;
+
+    while i < lines.len() {
+        if !is_code(&lines[i]) {
+            i += 1;
+            continue;
+        }
+
+        // blank lines inside the block are not marked as code
+        let start = i;
+        let mut end = i;
+        while i < lines.len() && (is_code(&lines[i]) || lines[i].is_empty()) {
+            if is_code(&lines[i]) {
+                end = i + 1;
+            }
+            i += 1;
+        }
+
+        let code: Vec<String> = lines[start..end].iter().map(|l| line_text(l)).collect();
+        let language = Language::guess(&code.join("\n"));
+        for (line, slot) in lines[start..end].iter().zip(&mut languages[start..end]) {
+            if is_code(line) {
+                *slot = Some(language);
+            }
+        }
+
+        i = end;
+    }
+
+    languages
+}
+
+/// Code line kept verbatim, cut at `width` cells with a marker instead of wrapping
+fn code_spans(line: &str, language: Language, width: usize) -> Vec<Span<'static>> {
+    let line = line.replace('\t', "    ");
+    let cut = text_width(&line) > width;
+    let line = if cut {
+        truncate_width(&line, width.saturating_sub(text_width(CODE_CUT_MSG)))
+    } else {
+        &line
+    };
+
+    let mut spans = vec![Span::raw(" ")];
+    spans.extend(
+        highlight_line(line, language)
+            .into_iter()
+            .map(|(text, token)| Span::styled(text, token_style(token))),
+    );
+    if cut {
+        spans.push(Span::styled(
+            CODE_CUT_MSG,
+            Style::default().fg(Color::Magenta),
+        ));
+    }
+
+    spans
+}
+
+fn token_style(token: Token) -> Style {
+    match token {
+        Token::Plain => Style::default().fg(Color::LightCyan),
+        Token::Keyword => Style::default()
+            .fg(Color::LightMagenta)
+            .add_modifier(Modifier::BOLD),
+        Token::String => Style::default().fg(Color::LightGreen),
+        Token::Number => Style::default().fg(Color::LightYellow),
+        Token::Comment => Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::ITALIC),
+    }
+}
+
+/// Targets of the quotelinks of the comment
+pub(crate) fn quote_links(com: &str) -> Vec<Link> {
+    html::links(&html::parse(com))
+}
+
+/// Text of every code block in the comment, for copying
+pub(crate) fn code_blocks(com: &str) -> Vec<String> {
+    html::code_blocks(&html::parse(com))
+}
+
+/// Styled spans of a row, indented by one space.
+///
+/// Hidden spoilers are drawn as solid blocks of the same width,
+/// links to posts missing from the loaded thread as dead links.
+fn row_spans(row: Vec<Segment>, display: PostDisplay) -> Vec<Span<'static>> {
+    row.into_iter()
+        .enumerate()
+        .map(|(i, mut segment)| {
+            if let (Some(no), Some(posts)) = (segment.markup.post_link, display.thread_posts) {
+                segment.markup.dead_link |= !posts.contains_key(&no);
+            }
+
+            let style = if segment.markup.spoiler && !display.reveal_spoilers {
+                segment.text = SPOILER_BLOCK.repeat(text_width(&segment.text));
+                Style::default().fg(Color::DarkGray)
+            } else {
+                markup_style(&segment.markup)
+            };
+
+            let text = if i == 0 {
+                format_default(&segment.text)
+            } else {
+                segment.text
+            };
+
+            Span::styled(text, style)
+        })
+        .collect()
+}
+
+fn markup_style(markup: &Markup) -> Style {
+    let mut style = Style::default();
+
+    if markup.quote {
+        style = style.fg(Color::Green);
+    }
+    if markup.quote_link {
+        style = style.fg(Color::Yellow);
+    }
+    if markup.cross_link {
+        style = style
+            .fg(Color::LightBlue)
+            .add_modifier(Modifier::UNDERLINED);
+    }
+    if markup.dead_link {
+        style = style
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::CROSSED_OUT);
+    }
+    if markup.code {
+        style = style.fg(Color::LightCyan);
+    }
+    if markup.exif {
+        style = style.fg(Color::DarkGray).add_modifier(Modifier::ITALIC);
+    }
+    if markup.spoiler {
+        style = style.add_modifier(Modifier::REVERSED);
+    }
+    if markup.bold {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if markup.italic {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if markup.underline {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+
+    style
+}
+
+/// Capcode label and its color, as on the site
+fn format_capcode(capcode: &str) -> (String, Color) {
+    let (role, color) = match capcode {
+        "admin" | "admin_highlight" => ("Admin", Color::Red),
+        "mod" => ("Mod", Color::Magenta),
+        "developer" => ("Developer", Color::Blue),
+        "manager" => ("Manager", Color::LightMagenta),
+        "founder" => ("Founder", Color::LightRed),
+        "verified" => ("Verified", Color::LightBlue),
+        other => (other, Color::Gray),
+    };
+
+    (format!("## {}", format_text(role)), color)
+}
+
+/// File size and dimensions, e.g. ` (512 KB, 1920x1080)`
+fn format_file_info(fsize: Option<u64>, w: Option<u32>, h: Option<u32>) -> String {
+    let size = fsize.map(format_file_size);
+    let dimensions = match (w, h) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => Some(format!("{}x{}", w, h)),
+        _ => None,
+    };
+
+    let info: Vec<String> = size.into_iter().chain(dimensions).collect();
+    if info.is_empty() {
+        "".to_string()
+    } else {
+        format!(" ({})", info.join(", "))
+    }
+}
+
+fn format_file_size(size: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+
+    match size {
+        size if size < KB => format!("{} B", size),
+        size if size < MB => format!("{} KB", (size + KB / 2) / KB),
+        size => format!("{:.2} MB", size as f64 / MB as f64),
+    }
+}
+
+/// Lines of the board info panel
+pub(crate) fn format_board_info(board: &Board) -> Vec<Spans<'static>> {
+    let label = Style::default().fg(Color::Magenta);
+    let row = |name: &str, value: String| {
+        Spans::from(vec![
+            Span::styled(format_default(&format!("{:<20}", name)), label),
+            Span::raw(value),
+        ])
+    };
+    let yes_no = |flag: bool| if flag { "yes" } else { "no" }.to_string();
+
+    let mut lines = vec![
+        Spans::from(Span::styled(
+            format_default(&format_text(&format!(
+                "/{}/ - {}",
+                board.board(),
+                board.title()
+            ))),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(format_default(&format_html(board.meta_description()))),
+        Spans::from(""),
+        row("Worksafe:", yes_no(!board.nsfw())),
+        row(
+            "Pages:",
+            format!("{}, {} threads each", board.pages(), board.per_page()),
+        ),
+        row("Bump limit:", board.bump_limit().to_string()),
+    ];
+
+    if let Some(image_limit) = board.image_limit() {
+        lines.push(row("Image limit:", image_limit.to_string()));
+    }
+    if let Some(max_filesize) = board.max_filesize() {
+        lines.push(row("Max file size:", format_file_size(max_filesize)));
+    }
+    if let Some(max_webm_filesize) = board.max_webm_filesize() {
+        lines.push(row("Max webm size:", format_file_size(max_webm_filesize)));
+    }
+    if let Some(max_comment_chars) = board.max_comment_chars() {
+        lines.push(row("Max comment length:", max_comment_chars.to_string()));
+    }
+    if let Some(cooldowns) = board.cooldowns() {
+        lines.push(row(
+            "Cooldowns:",
+            format!(
+                "threads {}s, replies {}s, images {}s",
+                cooldowns.threads(),
+                cooldowns.replies(),
+                cooldowns.images()
+            ),
+        ));
+    }
+
+    lines.push(row("Spoilers:", yes_no(board.spoilers() == 1)));
+    lines.push(row("Poster IDs:", yes_no(board.user_ids() == 1)));
+    lines.push(row("Country flags:", yes_no(board.country_flags() == 1)));
+    lines.push(row("Code tags:", yes_no(board.code_tags() == 1)));
+    lines.push(row("Forced anonymous:", yes_no(board.forced_anon() == 1)));
+    lines.push(row("Archive:", yes_no(board.is_archived() == 1)));
+
+    if let Some(flags) = board.board_flags() {
+        let names: Vec<String> = flags.values().map(|name| format_text(name)).collect();
+        lines.push(row("Board flags:", names.join(", ")));
+    }
+
+    lines
+}
+
+fn calc_width(area: Rect) -> u16 {
+    const MIN_WIDTH: i16 = 10;
+    const BORDERS: i16 = 4;
+
+    let area_width = area.width as i16;
+    let width = if area_width - BORDERS < MIN_WIDTH {
+        MIN_WIDTH
+    } else {
+        area_width - BORDERS
+    };
+
+    width as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_file_info() {
+        assert_eq!(format_file_info(None, None, None), "");
+        assert_eq!(format_file_info(Some(512), None, None), " (512 B)");
+        assert_eq!(
+            format_file_info(Some(123456), Some(800), Some(600)),
+            " (121 KB, 800x600)"
+        );
+        assert_eq!(
+            format_file_info(Some(3 * 1024 * 1024 + 1024 * 300), Some(1920), Some(1080)),
+            " (3.29 MB, 1920x1080)"
+        );
+        assert_eq!(format_file_info(None, Some(0), Some(0)), "");
+    }
+
+    #[test]
+    fn test_format_capcode() {
+        assert_eq!(
+            format_capcode("mod"),
+            ("## Mod".to_string(), Color::Magenta)
+        );
+        assert_eq!(
+            format_capcode("admin_highlight"),
+            ("## Admin".to_string(), Color::Red)
+        );
+        assert_eq!(
+            format_capcode("janitor"),
+            ("## janitor".to_string(), Color::Gray)
+        );
+    }
+
+    #[test]
+    fn test_format_default() {
+        assert_eq!(format_default("string"), " string");
+    }
+
+    #[test]
+    fn test_escape_sequences_are_sanitized() {
+        assert_eq!(format_html("&#27;]0;title&#7;"), "␛]0;title␇");
+        assert_eq!(format_html("a&#10;b &amp; &#x202E;c"), "a b & c");
+        assert_eq!(format_html("broken &#xZZ; entity"), "broken &#xZZ; entity");
+        assert_eq!(format_text("\x1b[31mflag\u{9b}"), "␛[31mflag\u{fffd}");
+
+        assert_eq!(
+            format_post_contents(
+                "&#27;[2J<b>&#27;[H</b>\x1b[0m",
+                50,
+                PostDisplay::new(false, Some(10))
+            ),
+            vec![Spans::from(vec![
+                Span::raw(" ␛[2J"),
+                Span::styled("␛[H", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw("␛[0m"),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_format_post_contents_markup() {
+        const POST: &str = "&gt;plain<br><span class=\"quote\">&gt;green</span> text<br>\
+            <a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a> <b>bold</b>";
+
+        assert_eq!(
+            format_post_contents(POST, 50, PostDisplay::new(false, Some(10))),
+            vec![
+                Spans::from(" >plain"),
+                Spans::from(vec![
+                    Span::styled(" >green", Style::default().fg(Color::Green)),
+                    Span::raw(" text"),
+                ]),
+                Spans::from(vec![
+                    Span::styled(" >>1", Style::default().fg(Color::Yellow)),
+                    Span::raw(" "),
+                    Span::styled("bold", Style::default().add_modifier(Modifier::BOLD)),
+                ]),
+            ]
+        );
+
+        // rows are split across segments
+        assert_eq!(
+            format_post_contents("ab<b>cdef</b>", 3, PostDisplay::new(false, Some(10))),
+            vec![
+                Spans::from(vec![
+                    Span::raw(" ab"),
+                    Span::styled("c", Style::default().add_modifier(Modifier::BOLD)),
+                ]),
+                Spans::from(Span::styled(
+                    " def",
+                    Style::default().add_modifier(Modifier::BOLD)
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_post_contents_links() {
+        const POST: &str = "<a href=\"#p1\" class=\"quotelink\">&gt;&gt;1</a> \
+            <a href=\"#p2\" class=\"quotelink\">&gt;&gt;2</a> \
+            <a href=\"/g/thread/3#p3\" class=\"quotelink\">&gt;&gt;&gt;/g/3</a>";
+        let thread_posts = HashMap::from([(1, 0)]);
+
+        let spans = format_post_contents(
+            POST,
+            50,
+            PostDisplay::new(false, None).with_thread_posts(&thread_posts),
+        );
+        assert_eq!(
+            spans,
+            vec![Spans::from(vec![
+                Span::styled(" >>1", Style::default().fg(Color::Yellow)),
+                Span::raw(" "),
+                Span::styled(
+                    ">>2",
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::CROSSED_OUT)
+                ),
+                Span::raw(" "),
+                Span::styled(
+                    ">>>/g/3",
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::UNDERLINED)
+                ),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_format_backlinks() {
+        assert!(format_backlinks(50, PostDisplay::new(false, None)).is_empty());
+
+        let link = Style::default().fg(Color::Yellow);
+        assert_eq!(
+            format_backlinks(13, PostDisplay::new(false, None).with_replies(&[12, 345])),
+            vec![
+                Spans::from(vec![Span::raw(" Replies: "), Span::styled(">>12", link)]),
+                Spans::from(Span::styled(" >>345", link)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_post_contents_spoilers() {
+        const POST: &str = "he dies <s>at the end</s>";
+
+        assert_eq!(
+            format_post_contents(POST, 50, PostDisplay::new(false, Some(10))),
+            vec![Spans::from(vec![
+                Span::raw(" he dies "),
+                Span::styled("██████████", Style::default().fg(Color::DarkGray)),
+            ])]
+        );
+        assert_eq!(
+            format_post_contents(POST, 50, PostDisplay::new(true, Some(10))),
+            vec![Spans::from(vec![
+                Span::raw(" he dies "),
+                Span::styled(
+                    "at the end",
+                    Style::default().add_modifier(Modifier::REVERSED)
+                ),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_format_post_contents_code() {
+        const POST: &str = "look<pre class=\"prettyprint\">fn main() {<br>\
+            \tlet very_long_name = 1;<br>}</pre>";
+
+        let spans = format_post_contents(POST, 20, PostDisplay::new(false, Some(10)));
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0], Spans::from(" look"));
+
+        // code is neither re-wrapped nor stripped of indentation
+        let text = |spans: &Spans| -> String {
+            spans.0.iter().map(|span| span.content.as_ref()).collect()
+        };
+        assert_eq!(text(&spans[1]), " fn main() {");
+        assert_eq!(text(&spans[2]), "     let very_long_n»");
+        assert_eq!(text(&spans[3]), " }");
+        assert_eq!(
+            spans[1].0[1],
+            Span::styled("fn", token_style(Token::Keyword))
+        );
+    }
+
+    #[test]
+    fn test_code_blocks() {
+        assert_eq!(
+            code_blocks("a<pre class=\"prettyprint\">x &lt; 1<br>  y</pre>b<pre>z</pre>"),
+            vec!["x < 1\n  y".to_string(), "z".to_string()]
+        );
+        assert!(code_blocks("no code").is_empty());
+    }
+
+    #[test]
+    fn test_format_post_contents() {
+        const POST: &str = "Natus est Schubert Himmelpfortgrund in vico Alsergrund Vindobonae \
+        die 31 Ianuarii 1797. Pater, Franciscus Theodorus Schubert, filius pagani Moraviani, \
+        magister scholae paroechialis; mater, Elisabeth (Vietz), filia artificis claustrarii \
+        Silesici fuit, quae ante nuptias ut ancilla in familia Vindobonensi laboraverat.";
+
+        // untruncated post formatting
+        assert_eq!(format_post_contents(POST, 100, PostDisplay::new(false, Some(5))), vec![
+            Spans::from(" Natus est Schubert Himmelpfortgrund in vico Alsergrund Vindobonae die 31 Ianuarii 1797. Pater,"),
+            Spans::from(" Franciscus Theodorus Schubert, filius pagani Moraviani, magister scholae paroechialis; mater,"),
+            Spans::from(" Elisabeth (Vietz), filia artificis claustrarii Silesici fuit, quae ante nuptias ut ancilla in"),
+            Spans::from(" familia Vindobonensi laboraverat."),
+        ]);
+
+        // truncated post formatting
+        assert_eq!(
+            format_post_contents(POST, 50, PostDisplay::new(false, Some(2))),
+            vec![
+                Spans::from(" Natus est Schubert Himmelpfortgrund in vico"),
+                Spans::from(" Alsergrund Vindobonae die 31 Ianuarii 1797. Pater,"),
+                Spans::from(vec![
+                    Span::from(" Franciscus Theodorus Schubert, filius pagani"),
+                    Span::styled("[...]", Style::default().fg(Color::Magenta))
+                ]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! Sanitization of text received from the API before it reaches the terminal.
+//!
+//! Control characters written to the terminal are interpreted by it, so an escape
+//! in a post could move the cursor, change the window title or corrupt the screen.
+
+/// Replace control characters with visible symbols and remove bidi controls.
+///
+/// Line breaks and tabs are kept for the callers that lay out lines, carriage returns are removed.
+pub(crate) fn sanitize(text: &str) -> String {
+    text.chars().filter_map(sanitize_char).collect()
+}
+
+/// Sanitize text shown on a single line, line breaks and tabs become spaces
+pub(crate) fn sanitize_line(text: &str) -> String {
+    text.chars()
+        .filter_map(sanitize_char)
+        .map(|ch| if ch == '\n' || ch == '\t' { ' ' } else { ch })
+        .collect()
+}
+
+fn sanitize_char(ch: char) -> Option<char> {
+    match ch {
+        '\n' | '\t' => Some(ch),
+        '\r' => None,
+        // C0 controls as their control pictures, e.g. ␛ for ESC
+        '\0'..='\x1f' => char::from_u32(0x2400 + ch as u32),
+        '\x7f' => Some('\u{2421}'),
+        '\u{80}'..='\u{9f}' => Some(char::REPLACEMENT_CHARACTER),
+        ch if is_bidi_control(ch) => None,
+        ch => Some(ch),
+    }
+}
+
+/// Characters changing the direction of the text around them
+fn is_bidi_control(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_control_characters() {
+        // window title change
+        assert_eq!(sanitize("\x1b]0;pwned\x07text"), "␛]0;pwned␇text");
+        // screen clear and cursor movement
+        assert_eq!(sanitize("\x1b[2J\x1b[1;1H"), "␛[2J␛[1;1H");
+        // 8-bit CSI
+        assert_eq!(sanitize("\u{9b}31mred"), "\u{fffd}31mred");
+        assert_eq!(sanitize("a\x08\x7fb"), "a␈␡b");
+        assert_eq!(sanitize("a\0b"), "a␀b");
+    }
+
+    #[test]
+    fn test_sanitize_bidi_controls() {
+        assert_eq!(sanitize("invoice\u{202e}fdp.exe"), "invoicefdp.exe");
+        assert_eq!(sanitize("\u{2066}a\u{2069}\u{200f}b\u{061c}"), "ab");
+        // right-to-left text itself is kept
+        assert_eq!(sanitize("שלום"), "שלום");
+    }
+
+    #[test]
+    fn test_sanitize_whitespace() {
+        assert_eq!(sanitize("line\r\n\tcode"), "line\n\tcode");
+        assert_eq!(sanitize_line("line\r\n\tnext\x1b"), "line  next␛");
+    }
+}
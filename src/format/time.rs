@@ -0,0 +1,257 @@
+//! Post timestamps, in the configured timezone or relative to the current time
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+
+/// Format of the imageboard, e.g. `04/07/21(Wed)15:47:19`
+pub(crate) const DEFAULT_PATTERN: &str = "%m/%d/%y(%a)%H:%M:%S";
+
+/// Common abbreviations, as the timezone of the region using them, daylight saving time included
+const ABBREVIATIONS: &[(&str, Tz)] = &[
+    ("est", Tz::America__New_York),
+    ("edt", Tz::America__New_York),
+    ("cst", Tz::America__Chicago),
+    ("cdt", Tz::America__Chicago),
+    ("mst", Tz::America__Denver),
+    ("mdt", Tz::America__Denver),
+    ("pst", Tz::America__Los_Angeles),
+    ("pdt", Tz::America__Los_Angeles),
+    ("bst", Tz::Europe__London),
+    ("cest", Tz::Europe__Berlin),
+    ("eest", Tz::Europe__Athens),
+    ("msk", Tz::Europe__Moscow),
+    ("ist", Tz::Asia__Kolkata),
+    ("jst", Tz::Asia__Tokyo),
+    ("kst", Tz::Asia__Seoul),
+    ("aest", Tz::Australia__Sydney),
+    ("aedt", Tz::Australia__Sydney),
+    ("nzst", Tz::Pacific__Auckland),
+    ("nzdt", Tz::Pacific__Auckland),
+];
+
+/// Timezone the timestamps are shown in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Timezone {
+    Local,
+    Utc,
+    Offset(FixedOffset),
+    /// IANA timezone, e.g. `Europe/Berlin`
+    Named(Tz),
+}
+
+impl Timezone {
+    /// Parse `local`, `utc`, an offset from UTC such as `+02:00`, `-0530` or `+3`,
+    /// an IANA name such as `Europe/Berlin` or a common abbreviation such as `PST`
+    pub(crate) fn parse(timezone: &str) -> Option<Self> {
+        let lowercase = timezone.to_ascii_lowercase();
+        match lowercase.as_str() {
+            "local" => return Some(Self::Local),
+            "utc" => return Some(Self::Utc),
+            _ => {}
+        }
+
+        let (sign, offset) = match timezone.split_at_checked(1)? {
+            ("+", offset) => (1, offset),
+            ("-", offset) => (-1, offset),
+            _ => {
+                let abbreviation = ABBREVIATIONS
+                    .iter()
+                    .find(|(abbreviation, _)| *abbreviation == lowercase)
+                    .map(|(_, tz)| *tz);
+                return abbreviation
+                    .or_else(|| {
+                        TZ_VARIANTS
+                            .iter()
+                            .find(|tz| tz.name().eq_ignore_ascii_case(timezone))
+                            .copied()
+                    })
+                    .map(Self::Named);
+            }
+        };
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+
+        let all_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        if !all_digits(hours) || !all_digits(minutes) {
+            return None;
+        }
+
+        let hours: i32 = hours.parse().ok()?;
+        let minutes: i32 = minutes.parse().ok()?;
+        if minutes >= 60 {
+            return None;
+        }
+
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).map(Self::Offset)
+    }
+}
+
+/// How post timestamps are shown, from the settings
+#[derive(Debug, PartialEq)]
+pub(crate) struct TimeFormat {
+    /// strftime pattern
+    pattern: String,
+    timezone: Timezone,
+    /// Show the age of the post instead of the date
+    relative: bool,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self {
+            pattern: DEFAULT_PATTERN.to_string(),
+            timezone: Timezone::Utc,
+            relative: false,
+        }
+    }
+}
+
+impl TimeFormat {
+    /// Time format with the strftime pattern, `None` if the pattern is invalid
+    pub(crate) fn new(pattern: &str, timezone: Timezone, relative: bool) -> Option<Self> {
+        if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+            return None;
+        }
+
+        Some(Self {
+            pattern: pattern.to_string(),
+            timezone,
+            relative,
+        })
+    }
+
+    /// Date of the timestamp in the configured timezone
+    fn format_date(&self, timestamp: u64) -> String {
+        let datetime = DateTime::<Utc>::from_timestamp(timestamp as i64, 0).unwrap_or_default();
+
+        match self.timezone {
+            Timezone::Local => datetime
+                .with_timezone(&Local)
+                .format(&self.pattern)
+                .to_string(),
+            Timezone::Utc => datetime.format(&self.pattern).to_string(),
+            Timezone::Offset(offset) => datetime
+                .with_timezone(&offset)
+                .format(&self.pattern)
+                .to_string(),
+            Timezone::Named(tz) => datetime
+                .with_timezone(&tz)
+                .format(&self.pattern)
+                .to_string(),
+        }
+    }
+}
+
+/// Time format together with the current time, for relative timestamps
+#[derive(Clone, Copy)]
+pub(crate) struct Clock<'a> {
+    format: &'a TimeFormat,
+    /// Current unix time, updated on every tick
+    now: u64,
+}
+
+impl<'a> Clock<'a> {
+    pub(crate) fn new(format: &'a TimeFormat, now: u64) -> Self {
+        Self { format, now }
+    }
+
+    /// Timestamp as a date, or as an age in relative mode
+    pub(crate) fn time(&self, timestamp: u64) -> String {
+        if self.format.relative {
+            self.age(timestamp)
+        } else {
+            self.format.format_date(timestamp)
+        }
+    }
+
+    /// Time passed since the timestamp, e.g. `3m ago`
+    pub(crate) fn age(&self, timestamp: u64) -> String {
+        const MINUTE: u64 = 60;
+        const HOUR: u64 = MINUTE * 60;
+        const DAY: u64 = HOUR * 24;
+        const YEAR: u64 = DAY * 365;
+
+        let age = self.now.saturating_sub(timestamp);
+        match age {
+            0 => "just now".to_string(),
+            age if age < MINUTE => format!("{}s ago", age),
+            age if age < HOUR => format!("{}m ago", age / MINUTE),
+            age if age < DAY => format!("{}h ago", age / HOUR),
+            age if age < YEAR => format!("{}d ago", age / DAY),
+            age => format!("{}y ago", age / YEAR),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(Timezone::parse("local"), Some(Timezone::Local));
+        assert_eq!(Timezone::parse("UTC"), Some(Timezone::Utc));
+        assert_eq!(
+            Timezone::parse("+02:00"),
+            FixedOffset::east_opt(2 * 3600).map(Timezone::Offset)
+        );
+        assert_eq!(
+            Timezone::parse("-0530"),
+            FixedOffset::west_opt(5 * 3600 + 30 * 60).map(Timezone::Offset)
+        );
+        assert_eq!(
+            Timezone::parse("+3"),
+            FixedOffset::east_opt(3 * 3600).map(Timezone::Offset)
+        );
+        assert_eq!(Timezone::parse("+25:00"), None);
+        assert_eq!(Timezone::parse("+02:60"), None);
+        assert_eq!(
+            Timezone::parse("europe/berlin"),
+            Some(Timezone::Named(Tz::Europe__Berlin))
+        );
+        assert_eq!(
+            Timezone::parse("PST"),
+            Some(Timezone::Named(Tz::America__Los_Angeles))
+        );
+        assert_eq!(Timezone::parse("Mars/Olympus_Mons"), None);
+        assert_eq!(Timezone::parse(""), None);
+    }
+
+    #[test]
+    fn test_format_time() {
+        let format = TimeFormat::default();
+        let clock = Clock::new(&format, 0);
+        assert_eq!(clock.time(1617810439), "04/07/21(Wed)15:47:19");
+        assert_eq!(clock.time(1717810439), "06/08/24(Sat)01:33:59");
+
+        let format =
+            TimeFormat::new("%Y-%m-%d %H:%M", Timezone::parse("-05:00").unwrap(), false).unwrap();
+        assert_eq!(Clock::new(&format, 0).time(1617810439), "2021-04-07 10:47");
+
+        // daylight saving time of the named timezone
+        let format =
+            TimeFormat::new("%H:%M", Timezone::parse("Europe/Berlin").unwrap(), false).unwrap();
+        assert_eq!(Clock::new(&format, 0).time(1617810439), "17:47");
+        assert_eq!(Clock::new(&format, 0).time(1609459200), "01:00");
+
+        assert_eq!(TimeFormat::new("%Q", Timezone::Utc, false), None);
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let format = TimeFormat::new(DEFAULT_PATTERN, Timezone::Utc, true).unwrap();
+        let clock = Clock::new(&format, 1_000_000);
+        assert_eq!(clock.time(1_000_000), "just now");
+        assert_eq!(clock.time(1_000_100), "just now");
+        assert_eq!(clock.time(1_000_000 - 45), "45s ago");
+        assert_eq!(clock.time(1_000_000 - 3 * 60 - 59), "3m ago");
+        assert_eq!(clock.time(1_000_000 - 2 * 3600), "2h ago");
+        assert_eq!(clock.time(1_000_000 - 9 * 86400), "9d ago");
+        assert_eq!(clock.age(0), "11d ago");
+        assert_eq!(Clock::new(&format, 2 * 365 * 86400).age(0), "2y ago");
+    }
+}
@@ -0,0 +1,266 @@
+//! Wrapping of styled text by display width
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use super::html::{Markup, Segment};
+
+/// Display width of the text in terminal cells
+pub(crate) fn text_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Split line into rows at most `width` cells wide.
+///
+/// Rows break at whitespace, which is dropped at the break. Words wider than a row
+/// are broken between graphemes, a single grapheme wider than a row gets a row of its own.
+pub(crate) fn wrap_segments(line: Vec<Segment>, width: usize) -> Vec<Vec<Segment>> {
+    let mut rows = vec![];
+    let mut row = Row::default();
+    // whitespace is only added once the word after it is on the same row
+    let mut space: Vec<(&str, Markup)> = vec![];
+
+    for word in words(&line) {
+        let word_width: usize = word.iter().map(|(g, _)| text_width(g)).sum();
+        let is_space = word
+            .first()
+            .is_some_and(|(g, _)| g.chars().all(char::is_whitespace));
+
+        if is_space {
+            // indentation is kept, spaces carried over to a new row are not
+            if rows.is_empty() && row.width == 0 {
+                if word_width <= width {
+                    row.push_all(&word);
+                }
+            } else {
+                space = word;
+            }
+            continue;
+        }
+
+        let space_width: usize = space.iter().map(|(g, _)| text_width(g)).sum();
+        let spaced_width = row.width + space_width;
+        let space = std::mem::take(&mut space);
+
+        if spaced_width + word_width <= width {
+            row.push_all(&space);
+            row.push_all(&word);
+            continue;
+        }
+
+        if word_width <= width {
+            row.flush(&mut rows);
+            row.push_all(&word);
+            continue;
+        }
+
+        // overlong word, break it between graphemes
+        if spaced_width < width {
+            row.push_all(&space);
+        } else {
+            row.flush(&mut rows);
+        }
+        for (grapheme, markup) in word {
+            let grapheme_width = text_width(grapheme);
+            if row.width + grapheme_width > width {
+                row.flush(&mut rows);
+            }
+            row.push(grapheme, markup);
+        }
+    }
+
+    row.flush(&mut rows);
+
+    rows
+}
+
+/// Remove graphemes from the end of the row until it is at most `width` cells wide
+pub(crate) fn cut_row(row: &mut Vec<Segment>, width: usize) {
+    let mut row_width: usize = row.iter().map(|segment| text_width(&segment.text)).sum();
+
+    while row_width > width {
+        let Some(segment) = row.last_mut() else {
+            return;
+        };
+
+        if let Some((i, grapheme)) = segment.text.grapheme_indices(true).next_back() {
+            row_width -= text_width(grapheme);
+            segment.text.truncate(i);
+        }
+
+        if segment.text.is_empty() {
+            row.pop();
+        }
+    }
+}
+
+/// Cut text to at most `width` cells, between graphemes
+pub(crate) fn truncate_width(text: &str, width: usize) -> &str {
+    let mut text_width = 0;
+    for (i, grapheme) in text.grapheme_indices(true) {
+        text_width += self::text_width(grapheme);
+        if text_width > width {
+            return &text[..i];
+        }
+    }
+
+    text
+}
+
+/// Row being filled, segments of the same markup are merged
+#[derive(Default)]
+struct Row {
+    segments: Vec<Segment>,
+    width: usize,
+}
+
+impl Row {
+    fn push(&mut self, grapheme: &str, markup: Markup) {
+        self.width += text_width(grapheme);
+        match self.segments.last_mut() {
+            Some(segment) if segment.markup == markup => segment.text.push_str(grapheme),
+            _ => self.segments.push(Segment::new(grapheme, markup)),
+        }
+    }
+
+    fn push_all(&mut self, word: &[(&str, Markup)]) {
+        for (grapheme, markup) in word {
+            self.push(grapheme, *markup);
+        }
+    }
+
+    /// Move the row to the finished rows, unless it is empty
+    fn flush(&mut self, rows: &mut Vec<Vec<Segment>>) {
+        if !self.segments.is_empty() {
+            self.width = 0;
+            rows.push(std::mem::take(&mut self.segments));
+        }
+    }
+}
+
+/// Graphemes of the line grouped into words and runs of whitespace
+fn words(line: &[Segment]) -> Vec<Vec<(&str, Markup)>> {
+    let mut words: Vec<Vec<(&str, Markup)>> = vec![];
+    let mut last_space = None;
+
+    for segment in line {
+        for grapheme in segment.text.graphemes(true) {
+            let space = grapheme.chars().all(char::is_whitespace);
+            match words.last_mut() {
+                Some(word) if last_space == Some(space) => word.push((grapheme, segment.markup)),
+                _ => words.push(vec![(grapheme, segment.markup)]),
+            }
+            last_space = Some(space);
+        }
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn wrap(text: &str, width: usize) -> Vec<String> {
+        wrap_segments(vec![Segment::new(text, Markup::default())], width)
+            .iter()
+            .map(|row| row.iter().map(|segment| segment.text.as_str()).collect())
+            .collect()
+    }
+
+    fn non_space_graphemes(text: &str) -> Vec<&str> {
+        text.graphemes(true)
+            .filter(|g| !g.chars().all(char::is_whitespace))
+            .collect()
+    }
+
+    #[test]
+    fn test_wrap_segments() {
+        assert_eq!(
+            wrap("the quick brown fox", 10),
+            vec!["the quick", "brown fox"]
+        );
+        assert_eq!(wrap("  indented", 20), vec!["  indented"]);
+        assert_eq!(wrap("abcdefghij klm", 4), vec!["abcd", "efgh", "ij", "klm"]);
+        // wide characters take two cells
+        assert_eq!(
+            wrap("日本語のテキスト", 7),
+            vec!["日本語", "のテキ", "スト"]
+        );
+        // combining marks stay with their base
+        assert_eq!(
+            wrap("e\u{301}e\u{301}e\u{301}", 2),
+            vec!["e\u{301}e\u{301}", "e\u{301}"]
+        );
+        // grapheme wider than the row
+        assert_eq!(wrap("日x", 1), vec!["日", "x"]);
+    }
+
+    #[test]
+    fn test_cut_row() {
+        let mut row = vec![
+            Segment::new("ab", Markup::default()),
+            Segment::new("日本", Markup::default()),
+        ];
+        cut_row(&mut row, 5);
+        assert_eq!(
+            row,
+            vec![
+                Segment::new("ab", Markup::default()),
+                Segment::new("日", Markup::default())
+            ]
+        );
+
+        cut_row(&mut row, 1);
+        assert_eq!(row, vec![Segment::new("a", Markup::default())]);
+    }
+
+    #[test]
+    fn test_truncate_width() {
+        assert_eq!(truncate_width("hello", 3), "hel");
+        assert_eq!(truncate_width("日本語", 5), "日本");
+        assert_eq!(truncate_width("e\u{301}x", 1), "e\u{301}");
+        assert_eq!(truncate_width("short", 10), "short");
+    }
+
+    proptest! {
+        #[test]
+        fn rows_fit_width(text in "\\PC*", width in 1usize..40) {
+            for row in wrap(&text, width) {
+                prop_assert!(
+                    text_width(&row) <= width || row.graphemes(true).count() == 1,
+                    "row {:?} is wider than {}", row, width
+                );
+            }
+        }
+
+        #[test]
+        fn graphemes_are_kept(text in "\\PC*", width in 1usize..40) {
+            let rows = wrap(&text, width);
+            let wrapped: Vec<&str> = rows.iter().flat_map(|row| non_space_graphemes(row)).collect();
+            prop_assert_eq!(wrapped, non_space_graphemes(&text));
+        }
+
+        #[test]
+        fn fitting_words_are_not_broken(
+            words in prop::collection::vec("[a-zа-я日本語😀]{1,8}", 1..20),
+            width in 16usize..40,
+        ) {
+            let text = words.join(" ");
+            let rows = wrap(&text, width);
+            let tokens: Vec<&str> = rows.iter().flat_map(|row| row.split_whitespace()).collect();
+            prop_assert_eq!(tokens, words.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+
+        #[test]
+        fn cut_row_fits_width(text in "\\PC*", width in 0usize..40) {
+            let mut row = vec![Segment::new(&text, Markup::default())];
+            cut_row(&mut row, width);
+            let cut: String = row.iter().map(|segment| segment.text.as_str()).collect();
+            prop_assert!(text_width(&cut) <= width);
+            prop_assert!(text.starts_with(&cut));
+        }
+    }
+}
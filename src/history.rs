@@ -0,0 +1,122 @@
+use std::fmt;
+
+/// Place on the imageboard that can be returned to
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Location {
+    pub(crate) board: String,
+    /// Opened thread, `None` for the thread list of the board
+    pub(crate) thread: Option<usize>,
+    /// Selected post of the thread
+    pub(crate) post: Option<usize>,
+}
+
+impl Location {
+    pub(crate) fn board(board: &str) -> Self {
+        Self {
+            board: board.to_string(),
+            thread: None,
+            post: None,
+        }
+    }
+
+    pub(crate) fn thread(board: &str, thread: usize, post: Option<usize>) -> Self {
+        Self {
+            board: board.to_string(),
+            thread: Some(thread),
+            post,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{}/", self.board)?;
+        if let Some(thread) = self.thread {
+            write!(f, "thread/{}", thread)?;
+        }
+        match self.post {
+            Some(post) if Some(post) != self.thread => write!(f, "#p{}", post),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Visited locations, browser-style
+#[derive(Debug)]
+pub(crate) struct History<T> {
+    back: Vec<T>,
+    forward: Vec<T>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        Self {
+            back: vec![],
+            forward: vec![],
+        }
+    }
+}
+
+impl<T> History<T> {
+    /// Leave `from` for a new location, which clears the forward history
+    pub(crate) fn visit(&mut self, from: T) {
+        self.back.push(from);
+        self.forward.clear();
+    }
+
+    /// Location before `current`, which can then be returned to with `forward`
+    pub(crate) fn back(&mut self, current: T) -> Option<T> {
+        let location = self.back.pop()?;
+        self.forward.push(current);
+
+        Some(location)
+    }
+
+    /// Location `back` was taken from, which can then be returned to with `back`
+    pub(crate) fn forward(&mut self, current: T) -> Option<T> {
+        let location = self.forward.pop()?;
+        self.back.push(current);
+
+        Some(location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let g = Location::board("g");
+        let thread = Location::thread("g", 1, Some(2));
+        let sci = Location::thread("sci", 5, None);
+
+        let mut history = History::default();
+        assert_eq!(history.back(g.clone()), None);
+
+        history.visit(g.clone());
+        history.visit(thread.clone());
+        assert_eq!(history.back(sci.clone()), Some(thread.clone()));
+        assert_eq!(history.back(thread.clone()), Some(g.clone()));
+        assert_eq!(history.back(g.clone()), None);
+        assert_eq!(history.forward(g.clone()), Some(thread.clone()));
+        assert_eq!(history.forward(thread.clone()), Some(sci.clone()));
+        assert_eq!(history.forward(sci.clone()), None);
+
+        // visiting drops the forward history
+        history.back(sci.clone());
+        history.visit(thread.clone());
+        assert_eq!(history.forward(g), None);
+    }
+
+    #[test]
+    fn test_display_location() {
+        assert_eq!(Location::board("g").to_string(), "/g/");
+        assert_eq!(Location::thread("g", 1, None).to_string(), "/g/thread/1");
+        assert_eq!(Location::thread("g", 1, Some(1)).to_string(), "/g/thread/1");
+        assert_eq!(
+            Location::thread("g", 1, Some(2)).to_string(),
+            "/g/thread/1#p2"
+        );
+    }
+}
@@ -60,9 +60,26 @@ macro_rules! define_keybinds {
         }
     };
 
-    // Use `Char` if no modifier given
+    // Use `Char` (or a special key name) if no modifier given
     (@modifier $mod:ident) => { Key::$mod };
-    (@modifier           ) => { Key::Char };
+    (@modifier           ) => { DefaultKey::into_key };
+}
+
+/// Default keybind without modifier: a character, or the name of a special key ("Esc")
+trait DefaultKey {
+    fn into_key(self) -> Key;
+}
+
+impl DefaultKey for char {
+    fn into_key(self) -> Key {
+        Key::Char(self)
+    }
+}
+
+impl DefaultKey for &str {
+    fn into_key(self) -> Key {
+        parse_keybind(self).expect("Default keybind must be valid")
+    }
 }
 
 define_keybinds! {
@@ -73,8 +90,8 @@ define_keybinds! {
     right              'd'  /// Move right
     quick_up      Ctrl 'w'  /// Move up quickly
     quick_down    Ctrl 's'  /// Move down quickly
-    quick_left    Ctrl 'a'  /// Move left quickly
-    quick_right   Ctrl 'd'  /// Move right quickly
+    quick_left    Ctrl 'a'  /// Select the previous reply to the post
+    quick_right   Ctrl 'd'  /// Select the next reply to the post
     page_next          'p'  /// Next page
     page_previous Ctrl 'p'  /// Previous page
     copy_thread        'c'  /// Copy the direct url to the selected thread or post
@@ -83,6 +100,26 @@ define_keybinds! {
     open_media    Ctrl 'o'  /// Open the selected post media (image/webm) in browser
     fullscreen         'z'  /// Toggle fullscreen for the selected panel
     reload             'r'  /// Reload page
+    cancel           "Esc"  /// Cancel loading
+    retry              'R'  /// Retry the last failed request
+    catalog            'C'  /// Toggle catalog view of the selected board
+    catalog_sort       'S'  /// Change catalog sort order
+    archive            'A'  /// Toggle archived threads of the selected board
+    board_info         'i'  /// Toggle info panel of the selected board
+    toggle_nsfw        'n'  /// Show / hide NSFW boards
+    reveal_spoilers    'x'  /// Reveal / hide spoilers of the selected post
+    copy_code          'y'  /// Copy the code blocks of the selected post
+    expand_post        'e'  /// Expand / collapse the selected post
+    read_post          'v'  /// Read the selected post in full, in an overlay
+    follow_quote       'f'  /// Jump to the post quoted by the selected post
+    quote_back         'b'  /// Jump back to the post a quote was followed from
+    preview_quotes     'l'  /// Preview the posts quoted by the selected or previewed post
+    conversation       'm'  /// Show only the posts quoting and quoted by the selected post
+    tree_view          't'  /// Toggle tree view of the thread, replies nested under the posts they quote
+    collapse_replies   'T'  /// Collapse / expand the replies nested under the selected post in tree view
+    cross_links        'g'  /// List the links of the selected post to other threads and boards
+    history_back       'B'  /// Go back to the previous location, as it was left
+    history_forward    'F'  /// Go forward again after going back
     help               'h'  /// Toggle help bar
     quit               'q'  /// Quit
     // Default `quit` keybind must also be updated in `event.rs`
@@ -129,7 +166,7 @@ pub enum KeybindsError {
 type KeyMap<'a> = HashMap<&'a str, Key>;
 
 /// Parse keybinds file, as hashmap
-fn parse_keymap_file(file: &str) -> Result<KeyMap, KeybindsError> {
+fn parse_keymap_file(file: &str) -> Result<KeyMap<'_>, KeybindsError> {
     let mut keymap = KeyMap::new();
 
     // Loop lines
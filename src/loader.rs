@@ -0,0 +1,211 @@
+use std::fmt;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use termion::event::Key;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+
+use crate::client::{ChanClient, ClientResult};
+use crate::event::Event;
+use crate::model::{Board, Thread, ThreadPost};
+
+/// Content that can be fetched in the background
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum Request {
+    Boards,
+    Threads { board: String, page: u8 },
+    Catalog { board: String },
+    Archive { board: String },
+    Thread { board: String, no: u64 },
+}
+
+impl Request {
+    /// Board the content is on, `None` for the board list
+    pub(crate) fn board(&self) -> Option<&str> {
+        match self {
+            Self::Boards => None,
+            Self::Threads { board, .. }
+            | Self::Catalog { board }
+            | Self::Archive { board }
+            | Self::Thread { board, .. } => Some(board),
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Boards => write!(f, "boards"),
+            Self::Threads { board, page } => write!(f, "/{}/ page {}", board, page),
+            Self::Catalog { board } => write!(f, "/{}/ catalog", board),
+            Self::Archive { board } => write!(f, "/{}/ archive", board),
+            Self::Thread { board, no } => write!(f, "thread /{}/{}", board, no),
+        }
+    }
+}
+
+pub(crate) enum Response {
+    Boards(Vec<Board>),
+    Threads(Vec<Thread>),
+    Thread {
+        posts: Vec<ThreadPost>,
+        /// Name of the archive the dead thread was fetched from
+        archive: Option<String>,
+    },
+}
+
+/// Result of a finished request, sent back through the `Events` channel
+pub(crate) struct Loaded {
+    id: u64,
+    request: Request,
+    result: ClientResult<Response>,
+}
+
+impl Loaded {
+    pub(crate) fn request(&self) -> &Request {
+        &self.request
+    }
+
+    pub(crate) fn into_result(self) -> ClientResult<Response> {
+        self.result
+    }
+}
+
+struct InFlight {
+    id: u64,
+    request: Request,
+    handle: JoinHandle<()>,
+}
+
+/// Spawns client requests on the runtime, keeping track of the one in flight.
+///
+/// Starting a new request or cancelling aborts the previous one,
+/// and results of aborted requests are never accepted.
+pub(crate) struct Loader {
+    client: Arc<ChanClient>,
+    runtime: Handle,
+    tx: Sender<Event<Key>>,
+    next_id: u64,
+    in_flight: Option<InFlight>,
+    failed: Option<Request>,
+}
+
+impl Loader {
+    pub(crate) fn new(client: Arc<ChanClient>, runtime: Handle, tx: Sender<Event<Key>>) -> Self {
+        Self {
+            client,
+            runtime,
+            tx,
+            next_id: 0,
+            in_flight: None,
+            failed: None,
+        }
+    }
+
+    pub(crate) fn load(&mut self, request: Request) {
+        self.cancel();
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let client = self.client.clone();
+        let tx = self.tx.clone();
+        let task_request = request.clone();
+
+        let handle = self.runtime.spawn(async move {
+            let result = match &task_request {
+                Request::Boards => client.get_boards().await.map(Response::Boards),
+                Request::Threads { board, page } => client
+                    .get_threads(board, *page)
+                    .await
+                    .map(Response::Threads),
+                Request::Catalog { board } => {
+                    client.get_catalog(board).await.map(Response::Threads)
+                }
+                Request::Archive { board } => {
+                    client.get_archive(board).await.map(Response::Threads)
+                }
+                Request::Thread { board, no } => client
+                    .get_thread(board, *no)
+                    .await
+                    .map(|(posts, archive)| Response::Thread { posts, archive }),
+            };
+
+            // the receiver only goes away when the app is shutting down
+            let _ = tx.send(Event::Loaded(Box::new(Loaded {
+                id,
+                request: task_request,
+                result,
+            })));
+        });
+
+        self.in_flight = Some(InFlight {
+            id,
+            request,
+            handle,
+        });
+    }
+
+    /// Abort the request in flight, returns `false` if there was none
+    pub(crate) fn cancel(&mut self) -> bool {
+        match self.in_flight.take() {
+            Some(in_flight) => {
+                in_flight.handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check that the result belongs to the request in flight and mark it as finished
+    pub(crate) fn accept(&mut self, loaded: &Loaded) -> bool {
+        match &self.in_flight {
+            Some(in_flight) if in_flight.id == loaded.id => {
+                self.in_flight = None;
+                self.failed = loaded.result.is_err().then(|| loaded.request.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Send the last failed request again, if there was one
+    pub(crate) fn retry(&mut self) {
+        if let Some(request) = self.failed.take() {
+            self.load(request);
+        }
+    }
+
+    pub(crate) fn loading_boards(&self) -> bool {
+        matches!(
+            self.in_flight,
+            Some(InFlight {
+                request: Request::Boards,
+                ..
+            })
+        )
+    }
+
+    pub(crate) fn loading_threads(&self) -> bool {
+        matches!(
+            self.in_flight,
+            Some(InFlight {
+                request: Request::Threads { .. }
+                    | Request::Catalog { .. }
+                    | Request::Archive { .. },
+                ..
+            })
+        )
+    }
+
+    pub(crate) fn loading_thread(&self) -> bool {
+        matches!(
+            self.in_flight,
+            Some(InFlight {
+                request: Request::Thread { .. },
+                ..
+            })
+        )
+    }
+}
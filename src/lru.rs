@@ -0,0 +1,74 @@
+//! Least recently used cache, for keeping a few of the fetched thread lists and threads
+
+/// Map of at most `capacity` entries, the least recently used one is dropped to make room
+pub(crate) struct Lru<K, V> {
+    capacity: usize,
+    /// Least recently used first
+    entries: Vec<(K, V)>,
+}
+
+impl<K: PartialEq, V> Lru<K, V> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Value of the key, which becomes the most recently used
+    pub(crate) fn get(&mut self, key: &K) -> Option<&mut V> {
+        let position = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(position);
+        self.entries.push(entry);
+
+        self.entries.last_mut().map(|(_, value)| value)
+    }
+
+    pub(crate) fn contains(&self, key: &K) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+
+    /// Insert the value as the most recently used, replacing the value of the same key
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.entries.retain(|(k, _)| *k != key);
+        if self.entries.len() == self.capacity {
+            self.entries.remove(0);
+        }
+        self.entries.push((key, value));
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.iter_mut().map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(2);
+        lru.insert(1, "a");
+        lru.insert(2, "b");
+        assert_eq!(lru.get(&1), Some(&mut "a"));
+
+        // 2 is the least recently used
+        lru.insert(3, "c");
+        assert!(!lru.contains(&2));
+        assert!(lru.contains(&1) && lru.contains(&3));
+
+        // replacing a value does not drop another
+        lru.insert(3, "d");
+        assert_eq!(lru.get(&3), Some(&mut "d"));
+        assert!(lru.contains(&1));
+
+        let mut empty = Lru::new(0);
+        empty.insert(1, "a");
+        assert!(!empty.contains(&1));
+    }
+}
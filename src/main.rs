@@ -1,8 +1,10 @@
 #![allow(clippy::single_match)]
 
+use std::sync::Arc;
+use std::time::Duration;
 use std::{env, io, process, str};
 
-use client::ChanClient;
+use client::{ChanClient, ClientError};
 use clipboard::{ClipboardContext, ClipboardProvider};
 use open::that as open_in_browser;
 use reqwest::Client;
@@ -11,35 +13,68 @@ use termion::raw::IntoRawMode;
 use termion::screen::AlternateScreen;
 use tokio::runtime::Runtime;
 use tui::backend::TermionBackend;
-use tui::layout::{Constraint, Direction, Layout};
+use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
-use tui::text::{Span, Spans};
-use tui::widgets::{Block, Borders, List, ListItem, Paragraph, Wrap};
+use tui::text::{Span, Spans, Text};
+use tui::widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap};
 use tui::Terminal;
 
-use crate::app::App;
+use crate::app::{App, Visit};
 use crate::client::api::{
-    from_name as channel_provider_from_name, ChannelProvider, ContentUrlProvider,
+    from_name as channel_provider_from_name, ApiUrlProvider, ChannelProvider, ContentUrlProvider,
 };
+use crate::client::providers::{
+    default_providers_file_contents, parse_providers_file, ProvidersFile,
+};
+use crate::config::read_or_create_config_file;
 use crate::event::{Event, Events};
-use crate::format::{format_default, format_post_full, format_post_short};
-use crate::keybinds::{read_or_create_keybinds_file, Keybinds};
-use crate::model::{Board, Thread, ThreadList, ThreadPost};
+use crate::format::time::Clock;
+use crate::format::{
+    code_blocks, format_board_info, format_default, format_post_full, format_post_reader,
+    format_post_short, format_text, PostDisplay,
+};
+use crate::keybinds::{display_key, read_or_create_keybinds_file, Keybinds};
+use crate::loader::{Loader, Request, Response};
+use crate::model::{Board, ThreadList, ThreadListMode};
+use crate::settings::Settings;
 use crate::style::{SelectedField, StyleProvider};
 
 mod app;
 mod client;
+mod config;
 mod event;
 mod format;
+mod history;
 mod keybinds;
+mod loader;
+mod lru;
 mod model;
+mod replies;
+mod settings;
 mod style;
+#[cfg(test)]
+mod test_util;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
 
 fn main() -> Result<(), io::Error> {
     // Get keybinds from config file
     let keybinds = read_or_create_keybinds_file().expect("Failed to read keybinds file");
     let keybinds = Keybinds::parse_from_file(&keybinds).expect("Failed to parse keybinds file");
 
+    // Get general settings from config file
+    let settings = read_or_create_config_file("settings.conf", Settings::default_file_contents)
+        .expect("Failed to read settings file");
+    let settings = Settings::parse_from_file(&settings).expect("Failed to parse settings file");
+
+    // Get user-defined imageboards from config file
+    let providers = read_or_create_config_file("providers.conf", default_providers_file_contents)
+        .expect("Failed to read providers file");
+    let ProvidersFile {
+        providers,
+        archives,
+    } = parse_providers_file(&providers).expect("Failed to parse providers file");
+
     let stdout = io::stdout().into_raw_mode()?;
     let stdout = MouseTerminal::from(stdout);
     let stdout = AlternateScreen::from(stdout);
@@ -50,7 +85,7 @@ fn main() -> Result<(), io::Error> {
     let args: Vec<String> = env::args().collect();
     let chan: &str = if args.len() == 1 { "default" } else { &args[1] };
 
-    let api: &dyn ChannelProvider = match channel_provider_from_name(chan) {
+    let api: &dyn ChannelProvider = match channel_provider_from_name(chan, providers) {
         Some(api) => api,
         None => {
             println!("Imageboard name \"{}\" is not valid.", chan);
@@ -58,41 +93,61 @@ fn main() -> Result<(), io::Error> {
         }
     };
 
-    let client = ChanClient::new(Client::new(), api.as_api());
+    let http_client = Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+    let client = Arc::new(ChanClient::new(
+        http_client,
+        api.as_api(),
+        api.as_normalizer(),
+        archives,
+    ));
     let events = Events::new();
+    let api_urls: &dyn ApiUrlProvider = api.as_api();
     let api: &dyn ContentUrlProvider = api.as_content();
 
-    let mut boards: Vec<Board> = vec![];
-    runtime.block_on(async {
-        let result = client.get_boards().await;
-
-        match result {
-            Ok(data) => boards = data,
-            Err(_) => panic!("Could not fetch boards"),
-        };
-    });
-
-    let mut app = App::new(boards, vec![], vec![], &keybinds);
+    let mut app = App::new(vec![], vec![], vec![], &keybinds);
     app.set_shown_board_list(true);
     let mut selected_field: SelectedField = SelectedField::BoardList;
     let mut thread_list = ThreadList::new();
     let style_prov = StyleProvider::new();
     let mut ctx: ClipboardContext = ClipboardProvider::new().unwrap();
+    let mut loader = Loader::new(client, runtime.handle().clone(), events.sender());
+    loader.load(Request::Boards);
 
     loop {
         terminal.draw(|f| {
             let block_style = style_prov.default_from_selected_field(&selected_field);
+            let clock = Clock::new(settings.time_format(), app.now());
             let scr_share = app.calc_screen_share();
 
             let mut constraints = vec![Constraint::Min(0)];
+            if app.status().is_some() {
+                constraints.push(Constraint::Length(1));
+            }
             if app.help_bar().shown() {
-                constraints.push(Constraint::Length(10));
+                constraints.push(Constraint::Length(app.help_bar().height()));
             }
 
             let helpbar_chunk = Layout::default()
                 .constraints::<&[Constraint]>(constraints.as_ref())
                 .split(f.size());
 
+            if let Some(status) = app.status() {
+                let paragraph = Paragraph::new(Spans::from(vec![
+                    Span::styled(
+                        format_default(&format_text(status)),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(
+                        format!(" (\"{}\" to retry)", display_key(&keybinds.retry)),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                ]));
+                f.render_widget(paragraph, helpbar_chunk[1]);
+            }
+
             if app.help_bar().shown() {
                 let block = Block::default().borders(Borders::NONE).title(Span::styled(
                     app.help_bar().title(),
@@ -103,7 +158,7 @@ fn main() -> Result<(), io::Error> {
                 let paragraph = Paragraph::new(app.help_bar().text().as_str())
                     .block(block)
                     .wrap(Wrap { trim: true });
-                f.render_widget(paragraph, helpbar_chunk[1]);
+                f.render_widget(paragraph, helpbar_chunk[helpbar_chunk.len() - 1]);
             }
 
             let chunks = Layout::default()
@@ -125,10 +180,10 @@ fn main() -> Result<(), io::Error> {
                 .map(|board| {
                     let lines = vec![Spans::from(vec![
                         Span::styled(
-                            format_default(&format!("/{}/", board.board())),
+                            format_default(&format_text(&format!("/{}/", board.board()))),
                             Style::default().fg(Color::Magenta),
                         ),
-                        Span::raw(format_default(board.title())),
+                        Span::raw(format_default(&format_text(board.title()))),
                     ])];
 
                     ListItem::new(lines).style(Style::default())
@@ -141,7 +196,15 @@ fn main() -> Result<(), io::Error> {
                         .borders(Borders::ALL)
                         .border_style(Style::default().fg(block_style.border_color().board_list()))
                         .border_type(block_style.border_type().board_list())
-                        .title(format_default("Boards ")),
+                        .title(format_default(&format!(
+                            "Boards {}{}",
+                            if app.show_nsfw() {
+                                ""
+                            } else {
+                                "(NSFW hidden) "
+                            },
+                            loading_title(loader.loading_boards(), app.spinner()),
+                        ))),
                 )
                 .highlight_style(
                     Style::default()
@@ -158,10 +221,14 @@ fn main() -> Result<(), io::Error> {
                 .iter()
                 .enumerate()
                 .map(|(i, thread)| {
+                    let op = thread.posts().first().unwrap();
                     format_post_short(
-                        thread.posts().first().unwrap(),
+                        thread,
                         i + 1,
                         thread_len,
+                        post_display(&app, &settings, op.no(), settings.thread_list_lines()),
+                        clock,
+                        settings.thread_ages(),
                         chunks[1],
                     )
                 })
@@ -174,9 +241,10 @@ fn main() -> Result<(), io::Error> {
                         .border_style(Style::default().fg(block_style.border_color().thread_list()))
                         .border_type(block_style.border_type().thread_list())
                         .title(format_default(&format!(
-                            "Threads, page {} {}",
-                            thread_list.cur_page(),
+                            "{} {}{}",
+                            thread_list.title(),
                             thread_list.description(),
+                            loading_title(loader.loading_threads(), app.spinner()),
                         ))),
                 )
                 .highlight_style(Style::default().bg(*style_prov.highlight_color()));
@@ -188,7 +256,23 @@ fn main() -> Result<(), io::Error> {
                 .items
                 .iter()
                 .enumerate()
-                .map(|(i, post)| format_post_full(post, i + 1, chunks[2]))
+                .map(|(i, post)| {
+                    // hidden posts take no space, so that positions in the list are kept
+                    if app.thread.hidden(i) {
+                        return ListItem::new(Text::default());
+                    }
+
+                    format_post_full(
+                        post,
+                        app.post_ordinal(i) + 1,
+                        post_display(&app, &settings, post.no(), settings.thread_lines())
+                            .with_thread_posts(app.thread_posts())
+                            .with_replies(app.reply_graph().replies(post.no()))
+                            .with_tree(app.post_depth(i), app.collapsed_replies(i)),
+                        clock,
+                        chunks[2],
+                    )
+                })
                 .collect();
 
             let thread = List::new(thread)
@@ -198,12 +282,125 @@ fn main() -> Result<(), io::Error> {
                         .border_style(Style::default().fg(block_style.border_color().thread()))
                         .border_type(block_style.border_type().thread())
                         .title(format_default(&format!(
-                            "Thread {}",
-                            app.selected_thread_description()
+                            "Thread {}{}",
+                            app.selected_thread_description(),
+                            loading_title(loader.loading_thread(), app.spinner()),
                         ))),
                 )
                 .highlight_style(Style::default().bg(*style_prov.highlight_color()));
             f.render_stateful_widget(thread, chunks[2], &mut app.thread.state);
+
+            for (depth, preview) in app.previews().iter().enumerate() {
+                let area = preview_area(chunks[2], depth as u16);
+                let posts: Vec<ListItem> = preview
+                    .posts()
+                    .iter()
+                    .filter_map(|no| app.thread_post(*no))
+                    .map(|(i, post)| {
+                        format_post_full(
+                            post,
+                            app.post_ordinal(i) + 1,
+                            post_display(&app, &settings, post.no(), settings.thread_lines())
+                                .with_thread_posts(app.thread_posts())
+                                .with_replies(app.reply_graph().replies(post.no())),
+                            clock,
+                            area,
+                        )
+                    })
+                    .collect();
+                let height = posts.iter().map(ListItem::height).sum::<usize>() as u16 + 2;
+                let area = Rect {
+                    height: height.min(area.height),
+                    ..area
+                };
+
+                let posts = List::new(posts)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title(format_default(&format!(
+                                "Quoted by >>{} (\"{}\" to close) ",
+                                preview.no(),
+                                display_key(&keybinds.cancel)
+                            ))),
+                    )
+                    .highlight_style(Style::default().bg(*style_prov.highlight_color()));
+                let mut state = preview.state.clone();
+                f.render_widget(Clear, area);
+                f.render_stateful_widget(posts, area, &mut state);
+            }
+
+            if let Some(board) = app.selected_board().filter(|_| app.shown_board_info()) {
+                let info = format_board_info(board);
+                let area = popup_area(helpbar_chunk[0], 70, info.len() as u16 + 2);
+                let info = Paragraph::new(info)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title(format_default(&format!(
+                                "Board info (\"{}\" to close) ",
+                                display_key(&keybinds.board_info)
+                            ))),
+                    )
+                    .wrap(Wrap { trim: false });
+                f.render_widget(Clear, area);
+                f.render_widget(info, area);
+            }
+
+            if let Some(menu) = app.link_menu() {
+                let links: Vec<ListItem> = menu
+                    .locations()
+                    .iter()
+                    .map(|location| {
+                        ListItem::new(format_default(&format_text(&location.to_string())))
+                    })
+                    .collect();
+                let area = popup_area(helpbar_chunk[0], 60, links.len() as u16 + 2);
+                let links = List::new(links)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title(format_default(&format!(
+                                "Links (\"{}\" to open) ",
+                                display_key(&keybinds.right)
+                            ))),
+                    )
+                    .highlight_style(Style::default().bg(*style_prov.highlight_color()));
+                let mut state = menu.state.clone();
+                f.render_widget(Clear, area);
+                f.render_stateful_widget(links, area, &mut state);
+            }
+
+            if app.reading() {
+                let area = popup_area(helpbar_chunk[0], 100, helpbar_chunk[0].height);
+                let lines = app
+                    .reader_post()
+                    .map(|post| {
+                        let reveal_spoilers =
+                            settings.reveal_spoilers() || app.spoilers_revealed(post.no());
+                        format_post_reader(post, reveal_spoilers, clock, area)
+                    })
+                    .unwrap_or_default();
+                let max_scroll = (lines.len() as u16).saturating_sub(area.height.saturating_sub(2));
+                let scroll = app.reader_scroll(max_scroll);
+
+                let reader = Paragraph::new(lines)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .border_style(Style::default().fg(Color::Magenta))
+                            .title(format_default(&format!(
+                                "Post (\"{}\" to close) ",
+                                display_key(&keybinds.read_post)
+                            ))),
+                    )
+                    .scroll((scroll, 0));
+                f.render_widget(Clear, area);
+                f.render_widget(reader, area);
+            }
         })?;
 
         match events.next().unwrap() {
@@ -215,11 +412,16 @@ fn main() -> Result<(), io::Error> {
                     match selected_field {
                         SelectedField::BoardList => {}
                         SelectedField::ThreadList => {
+                            loader.cancel();
+                            app.leave(&selected_field, &thread_list);
                             app.set_shown_board_list(true);
                             app.set_shown_thread(false);
                             selected_field = SelectedField::BoardList;
                         }
                         SelectedField::Thread => {
+                            loader.cancel();
+                            app.leave(&selected_field, &thread_list);
+                            app.close_previews();
                             app.set_shown_board_list(true);
                             app.set_shown_thread_list(true);
                             app.set_shown_thread(false);
@@ -227,6 +429,49 @@ fn main() -> Result<(), io::Error> {
                         }
                     };
                 }
+                _ if app.reading() && input == keybinds.down => {
+                    app.scroll_reader(1);
+                }
+                _ if app.reading() && input == keybinds.up => {
+                    app.scroll_reader(-1);
+                }
+                _ if app.reading() && input == keybinds.quick_down => {
+                    app.scroll_reader(5);
+                }
+                _ if app.reading() && input == keybinds.quick_up => {
+                    app.scroll_reader(-5);
+                }
+                _ if app.link_menu().is_some() && input == keybinds.down => {
+                    app.advance_link_menu(1);
+                }
+                _ if app.link_menu().is_some() && input == keybinds.up => {
+                    app.advance_link_menu(-1);
+                }
+                _ if app.link_menu().is_some() && input == keybinds.right => {
+                    let Some(location) = app.selected_link().cloned() else {
+                        continue;
+                    };
+                    app.close_link_menu();
+
+                    let from = app.visit(&selected_field, &thread_list);
+                    if let Some(field) = open_visit(
+                        &Visit::new(location),
+                        &mut app,
+                        &mut thread_list,
+                        &mut loader,
+                    ) {
+                        if let Some(from) = from {
+                            app.history_mut().visit(from);
+                        }
+                        selected_field = field;
+                    }
+                }
+                _ if app.previewing() && input == keybinds.down => {
+                    app.advance_preview(1);
+                }
+                _ if app.previewing() && input == keybinds.up => {
+                    app.advance_preview(-1);
+                }
                 _ if input == keybinds.down => {
                     const STEPS: isize = 1;
                     app.advance(&selected_field, STEPS);
@@ -276,7 +521,9 @@ fn main() -> Result<(), io::Error> {
                         SelectedField::Thread => app.url_thread(api),
                     };
 
-                    open_in_browser(url).expect("Browser error.");
+                    if let Some(url) = url {
+                        open_in_browser(url).expect("Browser error.");
+                    }
                 }
                 _ if input == keybinds.open_media => {
                     let url = match selected_field {
@@ -296,7 +543,9 @@ fn main() -> Result<(), io::Error> {
                         SelectedField::Thread => app.url_thread(api),
                     };
 
-                    ctx.set_contents(url).expect("Clipboard error.");
+                    if let Some(url) = url {
+                        ctx.set_contents(url).expect("Clipboard error.");
+                    }
                 }
                 _ if input == keybinds.copy_media => {
                     let url = match selected_field {
@@ -312,21 +561,18 @@ fn main() -> Result<(), io::Error> {
                 _ if input == keybinds.page_next => {
                     match selected_field {
                         SelectedField::ThreadList => {
-                            let mut threads: Vec<Thread> = vec![];
-                            runtime.block_on(async {
-                                let result = client
-                                    .get_threads(
-                                        app.selected_board().board(),
-                                        thread_list.next_page(app.selected_board()),
-                                    )
-                                    .await;
-                                match result {
-                                    Ok(data) => threads = data,
-                                    Err(err) => eprintln!("{:#?}", err),
-                                };
+                            let Some(board) = app.selected_board() else {
+                                continue;
+                            };
 
-                                app.fill_threads(threads);
-                            });
+                            if thread_list.mode() == ThreadListMode::Pages {
+                                let request = Request::Threads {
+                                    board: board.board().to_string(),
+                                    page: thread_list.next_page(board),
+                                };
+                                app.leave(&selected_field, &thread_list);
+                                open_thread_list(request, &mut app, &mut thread_list, &mut loader);
+                            }
                         }
                         _ => {}
                     };
@@ -334,62 +580,217 @@ fn main() -> Result<(), io::Error> {
                 _ if input == keybinds.page_previous => {
                     match selected_field {
                         SelectedField::ThreadList => {
-                            let mut threads: Vec<Thread> = vec![];
-                            runtime.block_on(async {
-                                let result = client
-                                    .get_threads(
-                                        app.selected_board().board(),
-                                        thread_list.prev_page(app.selected_board()),
-                                    )
-                                    .await;
-                                match result {
-                                    Ok(data) => threads = data,
-                                    Err(err) => eprintln!("{:#?}", err),
-                                };
+                            let Some(board) = app.selected_board() else {
+                                continue;
+                            };
 
-                                app.fill_threads(threads);
-                            });
+                            if thread_list.mode() == ThreadListMode::Pages {
+                                let request = Request::Threads {
+                                    board: board.board().to_string(),
+                                    page: thread_list.prev_page(board),
+                                };
+                                app.leave(&selected_field, &thread_list);
+                                open_thread_list(request, &mut app, &mut thread_list, &mut loader);
+                            }
                         }
                         _ => {}
                     };
                 }
                 _ if input == keybinds.reload => {
                     match selected_field {
+                        SelectedField::BoardList => {
+                            loader.load(Request::Boards);
+                        }
                         SelectedField::ThreadList => {
-                            let mut threads: Vec<Thread> = vec![];
-                            runtime.block_on(async {
-                                let result = client
-                                    .get_threads(
-                                        app.selected_board().board(),
-                                        thread_list.cur_page(),
-                                    )
-                                    .await;
-                                match result {
-                                    Ok(data) => threads = data,
-                                    Err(err) => eprintln!("{:#?}", err),
-                                };
-
-                                app.fill_threads(threads);
-                                app.threads.advance_by(1);
-                            });
+                            if let Some(board) = app.selected_board() {
+                                loader.load(thread_list_request(&thread_list, board));
+                            }
                         }
                         SelectedField::Thread => {
-                            let mut thread: Vec<ThreadPost> = vec![];
-                            runtime.block_on(async {
-                                let result = client
-                                    .get_thread(
-                                        app.selected_board().board(),
-                                        app.selected_thread().posts().first().unwrap().no() as u64,
-                                    )
-                                    .await;
-                                match result {
-                                    Ok(data) => thread = data,
-                                    Err(err) => eprintln!("{:#?}", err),
-                                };
+                            if app.thread_archived() {
+                                continue;
+                            }
 
-                                app.fill_thread(thread);
-                                app.thread.advance_by(1);
-                            });
+                            if let (Some(board), Some(no)) =
+                                (app.selected_board(), app.selected_thread_no())
+                            {
+                                loader.load(Request::Thread {
+                                    board: board.board().to_string(),
+                                    no,
+                                });
+                            }
+                        }
+                    };
+                }
+                _ if input == keybinds.cancel => {
+                    if app.reading() {
+                        app.close_reader();
+                    } else if app.link_menu().is_some() {
+                        app.close_link_menu();
+                    } else if app.previewing() {
+                        app.close_preview();
+                    } else if app.shown_board_info() {
+                        app.set_shown_board_info(false);
+                    } else if app.conversation().is_some()
+                        && matches!(selected_field, SelectedField::Thread)
+                    {
+                        app.close_conversation();
+                    } else {
+                        loader.cancel();
+                    }
+                }
+                _ if input == keybinds.board_info => {
+                    app.toggle_shown_board_info();
+                }
+                _ if input == keybinds.reveal_spoilers => {
+                    if let Some(no) = app.selected_post_in(&selected_field).map(|p| p.no()) {
+                        app.toggle_spoilers(no);
+                    }
+                }
+                _ if input == keybinds.copy_code => {
+                    let Some(post) = app.selected_post_in(&selected_field) else {
+                        continue;
+                    };
+
+                    let code = code_blocks(post.com());
+                    if !code.is_empty() {
+                        ctx.set_contents(code.join("\n\n"))
+                            .expect("Clipboard error.");
+                    }
+                }
+                _ if input == keybinds.expand_post => {
+                    if let Some(no) = app.selected_post_in(&selected_field).map(|p| p.no()) {
+                        app.toggle_expanded(no);
+                    }
+                }
+                _ if input == keybinds.read_post => {
+                    if app.reading() {
+                        app.close_reader();
+                    } else if let Some(no) = app.selected_post_in(&selected_field).map(|p| p.no()) {
+                        app.open_reader(no);
+                    }
+                }
+                _ if input == keybinds.quick_right => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        app.cycle_replies(1);
+                    }
+                }
+                _ if input == keybinds.quick_left => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        app.cycle_replies(-1);
+                    }
+                }
+                _ if input == keybinds.follow_quote => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        app.follow_quote();
+                    }
+                }
+                _ if input == keybinds.quote_back => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        app.quote_back();
+                    }
+                }
+                _ if input == keybinds.preview_quotes => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        app.open_preview();
+                    }
+                }
+                _ if input == keybinds.conversation => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        if app.conversation().is_some() {
+                            app.close_conversation();
+                        } else {
+                            app.open_conversation();
+                        }
+                    }
+                }
+                _ if input == keybinds.tree_view => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        app.toggle_tree_view();
+                    }
+                }
+                _ if input == keybinds.collapse_replies => {
+                    if matches!(selected_field, SelectedField::Thread) {
+                        app.toggle_collapsed();
+                    }
+                }
+                _ if input == keybinds.cross_links => {
+                    app.open_link_menu(&selected_field);
+                }
+                _ if input == keybinds.history_back => {
+                    let Some(current) = app.visit(&selected_field, &thread_list) else {
+                        continue;
+                    };
+
+                    if let Some(visit) = app.history_mut().back(current) {
+                        if let Some(field) =
+                            open_visit(&visit, &mut app, &mut thread_list, &mut loader)
+                        {
+                            selected_field = field;
+                        }
+                    }
+                }
+                _ if input == keybinds.history_forward => {
+                    let Some(current) = app.visit(&selected_field, &thread_list) else {
+                        continue;
+                    };
+
+                    if let Some(visit) = app.history_mut().forward(current) {
+                        if let Some(field) =
+                            open_visit(&visit, &mut app, &mut thread_list, &mut loader)
+                        {
+                            selected_field = field;
+                        }
+                    }
+                }
+                _ if input == keybinds.toggle_nsfw => {
+                    app.toggle_nsfw();
+                }
+                _ if input == keybinds.retry => {
+                    loader.retry();
+                    app.clear_status();
+                }
+                _ if input == keybinds.catalog => {
+                    match selected_field {
+                        SelectedField::ThreadList => {
+                            if let Some(board) = app.selected_board() {
+                                let from = thread_list.clone();
+                                thread_list.toggle_catalog();
+                                let request = thread_list_request(&thread_list, board);
+                                app.leave(&selected_field, &from);
+                                open_thread_list(request, &mut app, &mut thread_list, &mut loader);
+                            }
+                        }
+                        _ => {}
+                    };
+                }
+                _ if input == keybinds.archive => {
+                    match selected_field {
+                        SelectedField::ThreadList => {
+                            if let Some(board) = app.selected_board() {
+                                if api_urls.archive(board.board()).is_none() {
+                                    app.set_status(
+                                        "Archive is not supported by this imageboard".to_string(),
+                                    );
+                                    continue;
+                                }
+
+                                let from = thread_list.clone();
+                                thread_list.toggle_archive();
+                                let request = thread_list_request(&thread_list, board);
+                                app.leave(&selected_field, &from);
+                                open_thread_list(request, &mut app, &mut thread_list, &mut loader);
+                            }
+                        }
+                        _ => {}
+                    };
+                }
+                _ if input == keybinds.catalog_sort => {
+                    match selected_field {
+                        SelectedField::ThreadList => {
+                            if let Some(sort) = thread_list.next_sort() {
+                                app.sort_catalog(sort);
+                            }
                         }
                         _ => {}
                     };
@@ -397,49 +798,43 @@ fn main() -> Result<(), io::Error> {
                 _ if input == keybinds.right => {
                     match selected_field {
                         SelectedField::BoardList => {
-                            selected_field = SelectedField::ThreadList;
-                            app.set_shown_thread_list(true);
+                            let Some(board) = app.selected_board() else {
+                                continue;
+                            };
 
-                            thread_list = ThreadList::new();
-                            thread_list.set_description(app.selected_board().meta_description());
-                            let mut threads: Vec<Thread> = vec![];
-                            runtime.block_on(async {
-                                let result = client
-                                    .get_threads(
-                                        app.selected_board().board(),
-                                        thread_list.cur_page(),
-                                    )
-                                    .await;
-                                match result {
-                                    Ok(data) => threads = data,
-                                    Err(err) => eprintln!("{:#?}", err),
-                                };
+                            let from = thread_list.clone();
+                            let cached = app.board_request(board.board()).cloned();
+                            thread_list = ThreadList::with_mode(thread_list.mode());
+                            thread_list.set_description(board.meta_description());
+                            let request =
+                                cached.unwrap_or_else(|| thread_list_request(&thread_list, board));
 
-                                app.fill_threads(threads);
-                                app.threads.advance_by(1);
-                            });
+                            app.leave(&selected_field, &from);
+                            selected_field = SelectedField::ThreadList;
+                            app.set_shown_thread_list(true);
+                            if !open_thread_list(request, &mut app, &mut thread_list, &mut loader) {
+                                app.fill_threads(vec![]);
+                            }
                         }
                         SelectedField::ThreadList => {
+                            let (Some(board), Some(no)) =
+                                (app.selected_board(), app.selected_thread_no())
+                            else {
+                                continue;
+                            };
+
+                            let request = Request::Thread {
+                                board: board.board().to_string(),
+                                no,
+                            };
+
+                            app.leave(&selected_field, &thread_list);
                             selected_field = SelectedField::Thread;
                             app.set_shown_thread(true);
                             app.set_shown_board_list(false);
-
-                            let mut thread: Vec<ThreadPost> = vec![];
-                            runtime.block_on(async {
-                                let result = client
-                                    .get_thread(
-                                        app.selected_board().board(),
-                                        app.selected_thread().posts().first().unwrap().no() as u64,
-                                    )
-                                    .await;
-                                match result {
-                                    Ok(data) => thread = data,
-                                    Err(err) => eprintln!("{:#?}", err),
-                                };
-
-                                app.fill_thread(thread);
-                                app.thread.advance_by(1);
-                            });
+                            if !open_thread(request, &mut app, &mut loader) {
+                                app.fill_thread(vec![], None);
+                            }
                         }
                         _ => {}
                     };
@@ -449,8 +844,216 @@ fn main() -> Result<(), io::Error> {
             Event::Tick => {
                 app.advance_idly();
             }
+            Event::Loaded(loaded) => {
+                if !loader.accept(&loaded) {
+                    continue;
+                }
+
+                let request = loaded.request().clone();
+                match (request, loaded.into_result()) {
+                    (Request::Boards, Ok(Response::Boards(boards))) => {
+                        app.clear_status();
+                        app.fill_boards(boards);
+                        app.boards.advance_by(1);
+                    }
+                    (request @ Request::Threads { page, .. }, Ok(Response::Threads(threads))) => {
+                        app.clear_status();
+                        thread_list.set_page(page);
+                        app.fill_threads(threads);
+                        app.cache_threads(request, &thread_list);
+                        select_first_thread(&mut app);
+                    }
+                    (request @ Request::Archive { .. }, Ok(Response::Threads(threads))) => {
+                        app.clear_status();
+                        app.fill_threads(threads);
+                        app.cache_threads(request, &thread_list);
+                        select_first_thread(&mut app);
+                    }
+                    (request @ Request::Catalog { .. }, Ok(Response::Threads(threads))) => {
+                        app.clear_status();
+                        if let ThreadListMode::Catalog(sort) = thread_list.mode() {
+                            app.fill_catalog(threads, sort);
+                            app.cache_threads(request, &thread_list);
+                        }
+                        select_first_thread(&mut app);
+                    }
+                    (
+                        request @ Request::Thread { no, .. },
+                        Ok(Response::Thread { posts, archive }),
+                    ) => {
+                        app.clear_status();
+                        if archive.is_some() {
+                            app.mark_thread_dead(no);
+                        }
+                        app.fill_thread(posts, archive);
+                        app.cache_thread(request);
+                        select_first_post(&mut app);
+                    }
+                    (request, Err(err)) => {
+                        if let (Request::Thread { no, .. }, ClientError::NotFound) =
+                            (&request, &err)
+                        {
+                            app.mark_thread_dead(*no);
+                        }
+
+                        app.set_status(format!("Could not load {}: {}", request, err));
+                    }
+                    _ => {}
+                }
+            }
         }
     }
 
     Ok(())
 }
+
+/// Spoilers and length of the post, `line_limit` unless the post is expanded
+fn post_display(
+    app: &App,
+    settings: &Settings,
+    no: usize,
+    line_limit: usize,
+) -> PostDisplay<'static> {
+    PostDisplay::new(
+        settings.reveal_spoilers() || app.spoilers_revealed(no),
+        (!app.post_expanded(no)).then_some(line_limit),
+    )
+}
+
+fn loading_title(loading: bool, spinner: &str) -> String {
+    if loading {
+        format!(" {} loading… ", spinner)
+    } else {
+        "".to_string()
+    }
+}
+
+/// Go to the visited location, as it was left if it was visited before.
+///
+/// Returns the panel to select, `None` if the board of the location is not in the board list.
+fn open_visit(
+    visit: &Visit,
+    app: &mut App,
+    thread_list: &mut ThreadList,
+    loader: &mut Loader,
+) -> Option<SelectedField> {
+    app.remember(thread_list);
+    let board = &visit.location.board;
+    if !app.select_board(board) {
+        app.set_status(format!("Board /{}/ is not in the board list", board));
+        return None;
+    }
+
+    let board = app.selected_board()?;
+    *thread_list = match visit.thread_list() {
+        Some(visited) => visited.clone(),
+        None => {
+            let mut visited = ThreadList::with_mode(thread_list.mode());
+            visited.set_description(board.meta_description());
+            visited
+        }
+    };
+
+    let (selected_field, request) = app.restore(visit, thread_list_request(thread_list, board));
+    match request {
+        Some(request) => loader.load(request),
+        None => {
+            loader.cancel();
+        }
+    }
+
+    Some(selected_field)
+}
+
+/// Select the first thread of the loaded thread list, unless a thread to return to is selected
+fn select_first_thread(app: &mut App) {
+    if app.threads.state.selected().is_none() {
+        app.threads.advance_by(1);
+    }
+}
+
+/// Select the first post of the loaded thread, unless a post to return to or from a link is selected
+fn select_first_post(app: &mut App) {
+    if app.selected_post().is_none() {
+        app.thread.advance_by(1);
+    }
+}
+
+/// Show the thread list as it was left if it was loaded before, otherwise fetch it.
+///
+/// Returns `false` while it is being fetched.
+fn open_thread_list(
+    request: Request,
+    app: &mut App,
+    thread_list: &mut ThreadList,
+    loader: &mut Loader,
+) -> bool {
+    match app.restore_threads(&request) {
+        Some(cached) => {
+            *thread_list = cached;
+            loader.cancel();
+            select_first_thread(app);
+            true
+        }
+        None => {
+            loader.load(request);
+            false
+        }
+    }
+}
+
+/// Show the thread as it was left if it was loaded before, otherwise fetch it.
+///
+/// Returns `false` while it is being fetched.
+fn open_thread(request: Request, app: &mut App, loader: &mut Loader) -> bool {
+    if app.restore_thread(&request) {
+        loader.cancel();
+        select_first_post(app);
+        true
+    } else {
+        loader.load(request);
+        false
+    }
+}
+
+/// Area of the given size centered in `area`, shrunk to fit
+fn popup_area(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Area of a quote preview over the thread pane, nested previews are shifted down and right
+fn preview_area(area: Rect, depth: u16) -> Rect {
+    let x = (2 + depth * 2).min(area.width);
+    let y = (1 + depth).min(area.height);
+
+    Rect::new(
+        area.x + x,
+        area.y + y,
+        area.width.saturating_sub(x + 2),
+        area.height.saturating_sub(y + 1),
+    )
+}
+
+/// Request for the current page or the catalog of the board
+fn thread_list_request(thread_list: &ThreadList, board: &Board) -> Request {
+    match thread_list.mode() {
+        ThreadListMode::Pages => Request::Threads {
+            board: board.board().to_string(),
+            page: thread_list.cur_page(),
+        },
+        ThreadListMode::Catalog(_) => Request::Catalog {
+            board: board.board().to_string(),
+        },
+        ThreadListMode::Archive => Request::Archive {
+            board: board.board().to_string(),
+        },
+    }
+}
@@ -1,4 +1,6 @@
-use crate::format::format_html;
+use std::collections::BTreeMap;
+
+use crate::format::{format_html, quote_links, Link};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,6 +11,45 @@ pub(crate) struct Board {
     per_page: isize,
     pages: isize,
     bump_limit: isize,
+    /// `1` for worksafe boards, `0` for NSFW boards
+    #[serde(default)]
+    ws_board: Option<u8>,
+    #[serde(default)]
+    max_filesize: Option<u64>,
+    #[serde(default)]
+    max_webm_filesize: Option<u64>,
+    #[serde(default)]
+    max_comment_chars: Option<u32>,
+    #[serde(default)]
+    image_limit: Option<u32>,
+    #[serde(default)]
+    cooldowns: Option<Cooldowns>,
+    #[serde(default)]
+    spoilers: u8,
+    #[serde(default)]
+    user_ids: u8,
+    #[serde(default)]
+    country_flags: u8,
+    /// Flag codes and their names, for boards with custom flags
+    #[serde(default)]
+    board_flags: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    is_archived: u8,
+    #[serde(default)]
+    forced_anon: u8,
+    #[serde(default)]
+    code_tags: u8,
+}
+
+/// Seconds to wait between posts
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Cooldowns {
+    #[serde(default)]
+    threads: u32,
+    #[serde(default)]
+    replies: u32,
+    #[serde(default)]
+    images: u32,
 }
 
 impl Board {
@@ -24,61 +65,170 @@ impl Board {
         &self.meta_description
     }
 
-    #[allow(dead_code)]
     pub(crate) fn per_page(&self) -> isize {
         self.per_page
     }
 
-    #[allow(dead_code)]
     pub(crate) fn pages(&self) -> isize {
         self.pages
     }
 
-    #[allow(dead_code)]
     pub(crate) fn bump_limit(&self) -> isize {
         self.bump_limit
     }
+
+    /// Boards without the worksafe flag are not considered NSFW
+    pub(crate) fn nsfw(&self) -> bool {
+        self.ws_board == Some(0)
+    }
+
+    pub(crate) fn max_filesize(&self) -> Option<u64> {
+        self.max_filesize
+    }
+
+    pub(crate) fn max_webm_filesize(&self) -> Option<u64> {
+        self.max_webm_filesize
+    }
+
+    pub(crate) fn max_comment_chars(&self) -> Option<u32> {
+        self.max_comment_chars
+    }
+
+    pub(crate) fn image_limit(&self) -> Option<u32> {
+        self.image_limit
+    }
+
+    pub(crate) fn cooldowns(&self) -> Option<&Cooldowns> {
+        self.cooldowns.as_ref()
+    }
+
+    pub(crate) fn spoilers(&self) -> u8 {
+        self.spoilers
+    }
+
+    pub(crate) fn user_ids(&self) -> u8 {
+        self.user_ids
+    }
+
+    pub(crate) fn country_flags(&self) -> u8 {
+        self.country_flags
+    }
+
+    pub(crate) fn board_flags(&self) -> Option<&BTreeMap<String, String>> {
+        self.board_flags.as_ref()
+    }
+
+    pub(crate) fn is_archived(&self) -> u8 {
+        self.is_archived
+    }
+
+    pub(crate) fn forced_anon(&self) -> u8 {
+        self.forced_anon
+    }
+
+    pub(crate) fn code_tags(&self) -> u8 {
+        self.code_tags
+    }
 }
 
+impl Cooldowns {
+    pub(crate) fn threads(&self) -> u32 {
+        self.threads
+    }
+
+    pub(crate) fn replies(&self) -> u32 {
+        self.replies
+    }
+
+    pub(crate) fn images(&self) -> u32 {
+        self.images
+    }
+}
+
+#[derive(Clone)]
 pub struct ThreadList {
     page: u8,
     description: String,
+    mode: ThreadListMode,
 }
 
 impl ThreadList {
     const DEFAULT: u8 = 1;
 
     pub(crate) fn new() -> Self {
+        Self::with_mode(ThreadListMode::Pages)
+    }
+
+    pub(crate) fn with_mode(mode: ThreadListMode) -> Self {
         Self {
             page: Self::DEFAULT,
             description: "".to_string(),
+            mode,
         }
     }
 
-    pub(crate) fn next_page(&mut self, board: &Board) -> u8 {
-        if board.pages as u8 == self.page {
-            self.page = Self::DEFAULT;
+    pub(crate) fn mode(&self) -> ThreadListMode {
+        self.mode
+    }
+
+    /// Switch between paged threads and the catalog
+    pub(crate) fn toggle_catalog(&mut self) {
+        self.mode = match self.mode {
+            ThreadListMode::Catalog(_) => ThreadListMode::Pages,
+            _ => ThreadListMode::Catalog(CatalogSort::default()),
+        };
+    }
+
+    /// Switch between live and archived threads
+    pub(crate) fn toggle_archive(&mut self) {
+        self.mode = match self.mode {
+            ThreadListMode::Archive => ThreadListMode::Pages,
+            _ => ThreadListMode::Archive,
+        };
+    }
+
+    /// Change catalog sort order, returns `None` if not in catalog mode
+    pub(crate) fn next_sort(&mut self) -> Option<CatalogSort> {
+        if let ThreadListMode::Catalog(sort) = &mut self.mode {
+            *sort = sort.next();
+            Some(*sort)
         } else {
-            self.page += 1;
+            None
         }
+    }
 
-        self.page
+    pub(crate) fn title(&self) -> String {
+        match self.mode {
+            ThreadListMode::Pages => format!("Threads, page {}", self.page),
+            ThreadListMode::Catalog(sort) => format!("Catalog, by {}", sort.label()),
+            ThreadListMode::Archive => "Archived threads".to_string(),
+        }
     }
 
-    pub(crate) fn prev_page(&mut self, board: &Board) -> u8 {
-        if Self::DEFAULT == self.page {
-            self.page = board.pages as u8;
+    pub(crate) fn next_page(&self, board: &Board) -> u8 {
+        if board.pages as u8 == self.page {
+            Self::DEFAULT
         } else {
-            self.page -= 1;
+            self.page + 1
         }
+    }
 
-        self.page
+    pub(crate) fn prev_page(&self, board: &Board) -> u8 {
+        if Self::DEFAULT == self.page {
+            board.pages as u8
+        } else {
+            self.page - 1
+        }
     }
 
     pub(crate) fn cur_page(&self) -> u8 {
         self.page
     }
 
+    pub(crate) fn set_page(&mut self, page: u8) {
+        self.page = page;
+    }
+
     pub(crate) fn set_description(&mut self, desc: &str) {
         self.description = format_html(desc);
     }
@@ -88,18 +238,80 @@ impl ThreadList {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ThreadListMode {
+    /// Threads of a single board page, in bump order
+    Pages,
+    /// All live threads of the board at once
+    Catalog(CatalogSort),
+    /// Threads in the board archive
+    Archive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum CatalogSort {
+    #[default]
+    BumpOrder,
+    ReplyCount,
+    ImageCount,
+    CreationDate,
+    LastReply,
+}
+
+impl CatalogSort {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            Self::BumpOrder => Self::ReplyCount,
+            Self::ReplyCount => Self::ImageCount,
+            Self::ImageCount => Self::CreationDate,
+            Self::CreationDate => Self::LastReply,
+            Self::LastReply => Self::BumpOrder,
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Self::BumpOrder => "bump order",
+            Self::ReplyCount => "reply count",
+            Self::ImageCount => "image count",
+            Self::CreationDate => "creation date",
+            Self::LastReply => "last reply",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Thread {
     posts: Vec<ThreadPost>,
+    /// Thread returned 404, it was pruned or deleted
+    #[serde(skip)]
+    dead: bool,
 }
 
 impl Thread {
+    pub(crate) fn new(posts: Vec<ThreadPost>) -> Self {
+        Self { posts, dead: false }
+    }
+
     pub(crate) fn posts(&self) -> &[ThreadPost] {
         &self.posts
     }
+
+    /// Time of the latest known post, replies included
+    pub(crate) fn last_reply_time(&self) -> u64 {
+        self.posts.last().map_or(0, |post| post.time())
+    }
+
+    pub(crate) fn dead(&self) -> bool {
+        self.dead
+    }
+
+    pub(crate) fn set_dead(&mut self) {
+        self.dead = true;
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ThreadPost {
     #[serde(default)]
     no: usize,
@@ -122,14 +334,96 @@ pub struct ThreadPost {
     #[serde(default)]
     replies: u32,
     #[serde(default)]
+    images: u32,
+    #[serde(default)]
     ext: Option<String>,
     #[serde(default)]
     filename: Option<String>,
     #[serde(default)]
     tim: Option<u64>,
+    #[serde(default)]
+    resto: usize,
+    #[serde(default)]
+    trip: Option<String>,
+    #[serde(default)]
+    capcode: Option<String>,
+    #[serde(default)]
+    country: Option<String>,
+    #[serde(default)]
+    country_name: Option<String>,
+    #[serde(default)]
+    board_flag: Option<String>,
+    #[serde(default)]
+    flag_name: Option<String>,
+    /// File size in bytes
+    #[serde(default)]
+    fsize: Option<u64>,
+    #[serde(default)]
+    w: Option<u32>,
+    #[serde(default)]
+    h: Option<u32>,
+    #[serde(default)]
+    tn_w: Option<u32>,
+    #[serde(default)]
+    tn_h: Option<u32>,
+    #[serde(default)]
+    md5: Option<String>,
+    #[serde(default)]
+    spoiler: u8,
+    #[serde(default)]
+    custom_spoiler: Option<u32>,
+    #[serde(default)]
+    filedeleted: u8,
+    #[serde(default)]
+    omitted_posts: u32,
+    #[serde(default)]
+    omitted_images: u32,
+    #[serde(default)]
+    bumplimit: u8,
+    #[serde(default)]
+    imagelimit: u8,
+    #[serde(default)]
+    unique_ips: Option<u32>,
+    #[serde(default)]
+    archived_on: Option<u64>,
+    #[serde(default)]
+    semantic_url: Option<String>,
+    /// Category of the thread on /f/
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<u64>,
+    /// Path of the file on the media host, for imageboards without numeric `tim`
+    #[serde(default)]
+    file_path: Option<String>,
+    /// Full url of the file, for posts fetched from third-party archives
+    #[serde(default)]
+    file_url: Option<String>,
+    #[serde(default)]
+    archived: u8,
+    /// Only the number is known, the post itself has not been fetched
+    #[serde(skip)]
+    stub: bool,
 }
 
 impl ThreadPost {
+    /// Thread known only by its number
+    pub(crate) fn thread_stub(no: usize) -> Self {
+        Self {
+            no,
+            stub: true,
+            ..Self::default()
+        }
+    }
+
+    /// Archived thread known only by its number
+    pub(crate) fn archived_stub(no: usize) -> Self {
+        Self {
+            archived: 1,
+            ..Self::thread_stub(no)
+        }
+    }
+
     pub(crate) fn no(&self) -> usize {
         self.no
     }
@@ -146,6 +440,11 @@ impl ThreadPost {
         &self.com
     }
 
+    /// Targets of the quotelinks in the comment
+    pub(crate) fn links(&self) -> Vec<Link> {
+        quote_links(&self.com)
+    }
+
     pub(crate) fn sub(&self) -> &str {
         &self.sub
     }
@@ -162,6 +461,10 @@ impl ThreadPost {
         self.replies
     }
 
+    pub(crate) fn images(&self) -> u32 {
+        self.images
+    }
+
     pub(crate) fn ext(&self) -> &Option<String> {
         &self.ext
     }
@@ -170,10 +473,32 @@ impl ThreadPost {
         &self.filename
     }
 
+    #[allow(dead_code)]
     pub(crate) fn tim(&self) -> Option<u64> {
         self.tim
     }
 
+    /// Name of the file on the media host, if the post has a file
+    pub(crate) fn media_filename(&self) -> Option<String> {
+        if let Some(path) = &self.file_path {
+            return Some(path.clone());
+        }
+
+        Some(format!("{}{}", self.tim?, self.ext.as_ref()?))
+    }
+
+    pub(crate) fn file_url(&self) -> Option<&str> {
+        self.file_url.as_deref()
+    }
+
+    pub(crate) fn archived(&self) -> u8 {
+        self.archived
+    }
+
+    pub(crate) fn stub(&self) -> bool {
+        self.stub
+    }
+
     #[allow(dead_code)]
     pub(crate) fn now(&self) -> &str {
         &self.now
@@ -183,4 +508,118 @@ impl ThreadPost {
     pub(crate) fn id(&self) -> &str {
         &self.id
     }
+
+    #[allow(dead_code)]
+    pub(crate) fn resto(&self) -> usize {
+        self.resto
+    }
+
+    pub(crate) fn trip(&self) -> Option<&str> {
+        self.trip.as_deref()
+    }
+
+    pub(crate) fn capcode(&self) -> Option<&str> {
+        self.capcode.as_deref()
+    }
+
+    pub(crate) fn country(&self) -> Option<&str> {
+        self.country.as_deref()
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn country_name(&self) -> Option<&str> {
+        self.country_name.as_deref()
+    }
+
+    pub(crate) fn board_flag(&self) -> Option<&str> {
+        self.board_flag.as_deref()
+    }
+
+    pub(crate) fn flag_name(&self) -> Option<&str> {
+        self.flag_name.as_deref()
+    }
+
+    pub(crate) fn fsize(&self) -> Option<u64> {
+        self.fsize
+    }
+
+    pub(crate) fn w(&self) -> Option<u32> {
+        self.w
+    }
+
+    pub(crate) fn h(&self) -> Option<u32> {
+        self.h
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn tn_w(&self) -> Option<u32> {
+        self.tn_w
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn tn_h(&self) -> Option<u32> {
+        self.tn_h
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn md5(&self) -> Option<&str> {
+        self.md5.as_deref()
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn spoiler(&self) -> u8 {
+        self.spoiler
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn custom_spoiler(&self) -> Option<u32> {
+        self.custom_spoiler
+    }
+
+    pub(crate) fn filedeleted(&self) -> u8 {
+        self.filedeleted
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn omitted_posts(&self) -> u32 {
+        self.omitted_posts
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn omitted_images(&self) -> u32 {
+        self.omitted_images
+    }
+
+    pub(crate) fn bumplimit(&self) -> u8 {
+        self.bumplimit
+    }
+
+    pub(crate) fn imagelimit(&self) -> u8 {
+        self.imagelimit
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn unique_ips(&self) -> Option<u32> {
+        self.unique_ips
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn archived_on(&self) -> Option<u64> {
+        self.archived_on
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn semantic_url(&self) -> Option<&str> {
+        self.semantic_url.as_deref()
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn last_modified(&self) -> Option<u64> {
+        self.last_modified
+    }
 }
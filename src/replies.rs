@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::format::Link;
+use crate::model::ThreadPost;
+
+/// Quotes between the posts of a thread, by post number
+#[derive(Debug, Default)]
+pub(crate) struct ReplyGraph {
+    /// Posts quoted by each post, in order of the quotes
+    quotes: HashMap<usize, Vec<usize>>,
+    /// Posts quoting each post, in thread order
+    replies: HashMap<usize, Vec<usize>>,
+}
+
+impl ReplyGraph {
+    /// Graph of the thread on the board, quotes of posts missing from the thread are left out
+    pub(crate) fn new(posts: &[ThreadPost], board: Option<&str>) -> Self {
+        let mut graph = Self::default();
+        let Some(op) = posts.first() else {
+            return graph;
+        };
+        let numbers: HashSet<usize> = posts.iter().map(ThreadPost::no).collect();
+
+        for post in posts {
+            let quotes: Vec<usize> = post
+                .links()
+                .iter()
+                .filter_map(|link| link_target(link, op.no(), board))
+                .filter(|no| *no != post.no() && numbers.contains(no))
+                .fold(vec![], |mut quotes, no| {
+                    if !quotes.contains(&no) {
+                        quotes.push(no);
+                    }
+                    quotes
+                });
+
+            for quoted in &quotes {
+                graph.replies.entry(*quoted).or_default().push(post.no());
+            }
+            if !quotes.is_empty() {
+                graph.quotes.insert(post.no(), quotes);
+            }
+        }
+
+        graph
+    }
+
+    pub(crate) fn quotes(&self, no: usize) -> &[usize] {
+        self.quotes.get(&no).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn replies(&self, no: usize) -> &[usize] {
+        self.replies.get(&no).map_or(&[], Vec::as_slice)
+    }
+
+    /// Conversation of the post: the post itself, the posts it quotes and the posts quoting it, recursively
+    pub(crate) fn conversation(&self, no: usize) -> HashSet<usize> {
+        let mut conversation = HashSet::from([no]);
+        self.collect(no, Self::quotes, &mut conversation);
+        self.collect(no, Self::replies, &mut conversation);
+
+        conversation
+    }
+
+    /// Positions of the posts in tree order, with their depth in the tree.
+    ///
+    /// Each post is nested under the first earlier post it quotes, posts quoting none are at the top.
+    pub(crate) fn tree(&self, posts: &[ThreadPost]) -> Vec<(usize, usize)> {
+        let positions: HashMap<usize, usize> = posts
+            .iter()
+            .enumerate()
+            .map(|(i, post)| (post.no(), i))
+            .collect();
+
+        let mut roots = vec![];
+        let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, post) in posts.iter().enumerate() {
+            let parent = self
+                .quotes(post.no())
+                .iter()
+                .filter_map(|no| positions.get(no).copied())
+                .find(|parent| *parent < i);
+
+            match parent {
+                Some(parent) => children.entry(parent).or_default().push(i),
+                None => roots.push(i),
+            }
+        }
+
+        let mut tree = Vec::with_capacity(posts.len());
+        let mut pending: Vec<(usize, usize)> = roots.into_iter().rev().map(|i| (i, 0)).collect();
+        while let Some((i, depth)) = pending.pop() {
+            tree.push((i, depth));
+            if let Some(children) = children.get(&i) {
+                pending.extend(children.iter().rev().map(|child| (*child, depth + 1)));
+            }
+        }
+
+        tree
+    }
+
+    /// Add the posts reachable from the post through `edges` to `posts`
+    fn collect(&self, no: usize, edges: fn(&Self, usize) -> &[usize], posts: &mut HashSet<usize>) {
+        let mut pending = vec![no];
+        while let Some(no) = pending.pop() {
+            for &next in edges(self, no) {
+                if posts.insert(next) {
+                    pending.push(next);
+                }
+            }
+        }
+    }
+}
+
+/// Number of the post the link points to, if it is in the thread of `op_no` on the board
+pub(crate) fn link_target(link: &Link, op_no: usize, board: Option<&str>) -> Option<usize> {
+    match link {
+        Link::Post(no) => Some(*no),
+        Link::Thread {
+            board: link_board,
+            thread,
+            post,
+        } => {
+            let same_board = link_board
+                .as_deref()
+                .is_none_or(|link_board| Some(link_board) == board);
+            (same_board && *thread == op_no).then(|| post.unwrap_or(*thread))
+        }
+        Link::Board(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{post, quote, reply};
+
+    #[test]
+    fn test_reply_graph() {
+        let posts = vec![
+            post(1),
+            reply(2, &(quote(1) + &quote(1) + &quote(2))),
+            reply(3, &(quote(2) + &quote(1) + &quote(404))),
+            reply(
+                4,
+                "<a href=\"/g/thread/1#p3\" class=\"quotelink\">&gt;&gt;3</a>\
+                <a href=\"/v/thread/1#p2\" class=\"quotelink\">&gt;&gt;&gt;/v/2</a>",
+            ),
+        ];
+        let graph = ReplyGraph::new(&posts, Some("g"));
+
+        assert_eq!(graph.quotes(1), &[] as &[usize]);
+        assert_eq!(graph.quotes(2), &[1]);
+        assert_eq!(graph.quotes(3), &[2, 1]);
+        assert_eq!(graph.quotes(4), &[3]);
+
+        assert_eq!(graph.replies(1), &[2, 3]);
+        assert_eq!(graph.replies(2), &[3]);
+        assert_eq!(graph.replies(3), &[4]);
+        assert_eq!(graph.replies(4), &[] as &[usize]);
+    }
+
+    #[test]
+    fn test_conversation() {
+        let posts = vec![
+            post(1),
+            reply(2, &quote(1)),
+            reply(3, &quote(2)),
+            reply(4, &quote(1)),
+            reply(5, &quote(3)),
+            reply(6, &(quote(4) + &quote(5))),
+            post(7),
+        ];
+        let graph = ReplyGraph::new(&posts, None);
+        let conversation = |no| {
+            let mut posts: Vec<usize> = graph.conversation(no).into_iter().collect();
+            posts.sort();
+            posts
+        };
+
+        // ancestors and descendants, but not siblings
+        assert_eq!(conversation(3), vec![1, 2, 3, 5, 6]);
+        assert_eq!(conversation(4), vec![1, 4, 6]);
+        assert_eq!(conversation(1), vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(conversation(7), vec![7]);
+    }
+
+    #[test]
+    fn test_tree() {
+        let posts = vec![
+            post(1),
+            reply(2, &quote(1)),
+            reply(3, &(quote(5) + &quote(1))),
+            reply(4, &quote(2)),
+            reply(5, &quote(2)),
+            post(6),
+            reply(7, &(quote(404) + &quote(4))),
+        ];
+        let graph = ReplyGraph::new(&posts, None);
+        let tree: Vec<(usize, usize)> = graph
+            .tree(&posts)
+            .into_iter()
+            .map(|(i, depth)| (posts[i].no(), depth))
+            .collect();
+
+        assert_eq!(
+            tree,
+            vec![
+                (1, 0),
+                (2, 1),
+                (4, 2),
+                (7, 3),
+                (5, 2),
+                // quotes of later posts are skipped
+                (3, 1),
+                (6, 0),
+            ]
+        );
+    }
+}
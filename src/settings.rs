@@ -0,0 +1,228 @@
+use crate::config::{parse_sections, ConfigError, Entry};
+use crate::format::time::{TimeFormat, Timezone, DEFAULT_PATTERN};
+
+/// General settings from `settings.conf`
+#[derive(Debug, PartialEq)]
+pub(crate) struct Settings {
+    /// Show spoilers of all posts, without revealing them one by one
+    reveal_spoilers: bool,
+    time_format: TimeFormat,
+    /// Show age of threads and of their last reply in the thread list
+    thread_ages: bool,
+    /// Comment lines of a post shown in the thread list, before it is cut
+    thread_list_lines: usize,
+    /// Comment lines of a post shown in the thread, before it is cut
+    thread_lines: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            reveal_spoilers: false,
+            time_format: TimeFormat::default(),
+            thread_ages: false,
+            thread_list_lines: 10,
+            thread_lines: 60,
+        }
+    }
+}
+
+impl Settings {
+    pub(crate) fn reveal_spoilers(&self) -> bool {
+        self.reveal_spoilers
+    }
+
+    pub(crate) fn time_format(&self) -> &TimeFormat {
+        &self.time_format
+    }
+
+    pub(crate) fn thread_ages(&self) -> bool {
+        self.thread_ages
+    }
+
+    pub(crate) fn thread_list_lines(&self) -> usize {
+        self.thread_list_lines
+    }
+
+    pub(crate) fn thread_lines(&self) -> usize {
+        self.thread_lines
+    }
+
+    /// Parse settings file, settings not given in file keep their default value
+    pub(crate) fn parse_from_file(file: &str) -> Result<Self, ConfigError> {
+        let mut settings = Self::default();
+        let mut pattern = DEFAULT_PATTERN;
+        let mut timezone = Timezone::Utc;
+        let mut relative = false;
+
+        for section in parse_sections(file)? {
+            // Settings are not grouped in sections
+            if !section.name.is_empty() {
+                return Err(ConfigError::InvalidSection {
+                    line_no: section.line_no,
+                });
+            }
+
+            for entry in &section.entries {
+                match entry.key {
+                    "reveal_spoilers" => settings.reveal_spoilers = parse_bool(entry)?,
+                    "time_format" => {
+                        // validated here to report the line of the invalid pattern
+                        TimeFormat::new(entry.value, Timezone::Utc, false)
+                            .ok_or_else(|| invalid_value(entry))?;
+                        pattern = entry.value;
+                    }
+                    "timezone" => {
+                        timezone =
+                            Timezone::parse(entry.value).ok_or_else(|| invalid_value(entry))?
+                    }
+                    "relative_time" => relative = parse_bool(entry)?,
+                    "thread_ages" => settings.thread_ages = parse_bool(entry)?,
+                    "thread_list_lines" => settings.thread_list_lines = parse_count(entry)?,
+                    "thread_lines" => settings.thread_lines = parse_count(entry)?,
+                    key => {
+                        return Err(ConfigError::UnknownKey {
+                            line_no: entry.line_no,
+                            key: key.to_string(),
+                        })
+                    }
+                }
+            }
+        }
+
+        settings.time_format = TimeFormat::new(pattern, timezone, relative).unwrap_or_default();
+
+        Ok(settings)
+    }
+
+    /// Get contents of settings file, with default configuration
+    pub(crate) fn default_file_contents() -> String {
+        String::from(
+            r#"# Settings for tui-chan
+# https://github.com/tuqqu/tui-chan
+
+# Always show spoilers, instead of revealing them per post (true or false)
+reveal_spoilers=false
+
+# Format of post dates, see https://docs.rs/chrono/latest/chrono/format/strftime/
+time_format=%m/%d/%y(%a)%H:%M:%S
+
+# Timezone of post dates: local, utc, a name such as Europe/Berlin or PST,
+# or an offset such as +02:00
+timezone=utc
+
+# Show how long ago posts were made instead of their dates (true or false)
+relative_time=false
+
+# Show age of threads and of their last reply in the thread list (true or false)
+thread_ages=false
+
+# Comment lines of a post shown before it is cut, in the thread list and in the thread
+thread_list_lines=10
+thread_lines=60
+"#,
+        )
+    }
+}
+
+fn parse_bool(entry: &Entry) -> Result<bool, ConfigError> {
+    match entry.value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(invalid_value(entry)),
+    }
+}
+
+/// Positive number
+fn parse_count(entry: &Entry) -> Result<usize, ConfigError> {
+    match entry.value.parse() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(invalid_value(entry)),
+    }
+}
+
+fn invalid_value(entry: &Entry) -> ConfigError {
+    ConfigError::InvalidValue {
+        line_no: entry.line_no,
+        key: entry.key.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_settings_file_works() {
+        assert_eq!(
+            Settings::parse_from_file(&Settings::default_file_contents()),
+            Ok(Settings::default())
+        );
+        assert!(Settings::parse_from_file("reveal_spoilers=true")
+            .unwrap()
+            .reveal_spoilers());
+
+        assert_eq!(
+            Settings::parse_from_file("reveal_spoilers=yes"),
+            Err(ConfigError::InvalidValue {
+                line_no: 1,
+                key: "reveal_spoilers".to_string()
+            })
+        );
+        assert_eq!(
+            Settings::parse_from_file("\nunknown=1"),
+            Err(ConfigError::UnknownKey {
+                line_no: 2,
+                key: "unknown".to_string()
+            })
+        );
+        assert_eq!(
+            Settings::parse_from_file("[section]\nreveal_spoilers=true"),
+            Err(ConfigError::InvalidSection { line_no: 1 })
+        );
+    }
+
+    #[test]
+    fn parse_line_limits_works() {
+        let settings = Settings::parse_from_file("thread_list_lines=3\nthread_lines=200").unwrap();
+        assert_eq!(settings.thread_list_lines(), 3);
+        assert_eq!(settings.thread_lines(), 200);
+
+        for file in ["thread_lines=0", "thread_lines=-1", "thread_lines=many"] {
+            assert_eq!(
+                Settings::parse_from_file(file),
+                Err(ConfigError::InvalidValue {
+                    line_no: 1,
+                    key: "thread_lines".to_string()
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn parse_time_settings_works() {
+        let settings =
+            Settings::parse_from_file("relative_time=true\ntimezone=+01:30\ntime_format=%H:%M")
+                .unwrap();
+        assert_eq!(
+            settings.time_format(),
+            &TimeFormat::new("%H:%M", Timezone::parse("+01:30").unwrap(), true).unwrap()
+        );
+        assert!(!settings.thread_ages());
+
+        assert_eq!(
+            Settings::parse_from_file("time_format=%Y %Q"),
+            Err(ConfigError::InvalidValue {
+                line_no: 1,
+                key: "time_format".to_string()
+            })
+        );
+        assert_eq!(
+            Settings::parse_from_file("timezone=mars"),
+            Err(ConfigError::InvalidValue {
+                line_no: 1,
+                key: "timezone".to_string()
+            })
+        );
+    }
+}
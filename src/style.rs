@@ -39,6 +39,7 @@ impl StyleProvider {
     }
 }
 
+#[derive(Clone, Copy)]
 pub(crate) enum SelectedField {
     BoardList,
     ThreadList,
@@ -0,0 +1,17 @@
+//! Helpers shared by the tests of several modules
+
+use crate::model::ThreadPost;
+
+pub(crate) fn post(no: usize) -> ThreadPost {
+    serde_json::from_value(serde_json::json!({ "no": no })).unwrap()
+}
+
+/// Post with the comment, usually quoting others
+pub(crate) fn reply(no: usize, com: &str) -> ThreadPost {
+    serde_json::from_value(serde_json::json!({ "no": no, "com": com })).unwrap()
+}
+
+/// Comment quoting the post of the same thread
+pub(crate) fn quote(no: usize) -> String {
+    format!(r##"<a href="#p{no}" class="quotelink">&gt;&gt;{no}</a>"##)
+}